@@ -0,0 +1,69 @@
+use crate::utils::*;
+use crate::enums::SplitMod;
+
+use std::collections::HashMap;
+use near_sdk::AccountId;
+use near_sdk::json_types::U128;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Serialize, Deserialize};
+
+
+/// Extra fungible token of a bundle red packet, every claimer of the red packet gets a share of it
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BundleLeg {
+    pub token_id: AccountId,
+    pub split_mod: SplitMod,
+    pub init_balance: U128,
+    pub current_balance: U128,
+    pub refunded_balance: U128,
+    pub claimers: HashMap<AccountId, U128>,
+    pub failed_claimers: HashMap<AccountId, U128>
+}
+
+impl BundleLeg {
+    pub fn new(token_id: AccountId, amount: U128, split_mod: SplitMod) -> Self {
+        Self {
+            token_id,
+            split_mod,
+            init_balance: amount,
+            current_balance: amount,
+            refunded_balance: U128(0),
+            claimers: HashMap::new(),
+            failed_claimers: HashMap::new()
+        }
+    }
+
+    /// Only average and random split mod are supported
+    pub fn is_valid(&self, split: usize) -> bool {
+        matches!(self.split_mod, SplitMod::Average | SplitMod::Random)
+            && self.init_balance.0 >= split as u128
+    }
+
+    /// `split` is the current split of red packet before this claim, `nonce` makes random legs independent
+    pub fn virtual_claim(&mut self, claimer_id: AccountId, split: usize, nonce: u128) -> U128 {
+        let claim_amount = match self.split_mod {
+            SplitMod::Random => {
+                let (min_sub, max_sub) = random_sub_range(self.current_balance.0, split, None);
+                gen_range_with_nonce(min_sub, max_sub + 1, nonce)
+            },
+            _ => average_sub(self.current_balance.0, split)
+        };
+        self.claimers.insert(claimer_id, claim_amount.into());
+        self.current_balance.0 -= claim_amount;
+        claim_amount.into()
+    }
+
+    pub fn virtual_refund(&mut self) -> U128 {
+        let refund_amount = self.current_balance;
+        self.refunded_balance.0 += refund_amount.0;
+        self.current_balance = U128(0);
+        refund_amount
+    }
+
+    pub fn failed_claimer(&mut self, claimer_id: AccountId, failed_amount: U128) {
+        self.claimers.remove(&claimer_id);
+        self.failed_claimers.insert(claimer_id, failed_amount);
+        self.refunded_balance.0 += failed_amount.0;
+    }
+}
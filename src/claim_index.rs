@@ -0,0 +1,79 @@
+use crate::Contract;
+use crate::enums::StorageKey;
+use crate::dynamic_storage_management::DynamicStorageBasic;
+
+use near_sdk::{AccountId, env, PublicKey};
+use near_sdk::collections::Vector;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Serialize, Deserialize};
+
+
+/// One claim of an account, kept in the claimer index
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimRecord {
+    pub public_key: PublicKey,
+    pub amount: U128,
+    pub timestamp: U64,
+    /// Cleared when the transfer to the claimer fails and the amount goes back to the owner
+    pub success: bool
+}
+
+impl Contract {
+    /// Index the claim when the claimer is registered with enough storage balance, skip it otherwise.
+    /// Storage is charged to the claimer.
+    pub fn internal_record_claim(&mut self, claimer_id: &AccountId, public_key: PublicKey, amount: U128) {
+        if !self.storage_manager.account_registered(claimer_id) {
+            return;
+        };
+
+        self.measure_start();
+        let mut claims = self.claims.get(claimer_id).unwrap_or_else(|| {
+            Vector::new(StorageKey::AccountClaims { account_hash: env::sha256(claimer_id.as_bytes()) })
+        });
+        claims.push(&ClaimRecord {
+            public_key,
+            amount,
+            timestamp: U64(env::block_timestamp()),
+            success: true
+        });
+        self.claims.insert(claimer_id, &claims);
+        self.measure_end(claimer_id);
+
+        if !self.storage_manager.enough_storage_balance(claimer_id) {
+            self.measure_start();
+            claims.pop();
+            if claims.is_empty() {
+                self.claims.remove(claimer_id);
+            } else {
+                self.claims.insert(claimer_id, &claims);
+            };
+            self.measure_end(claimer_id);
+        };
+    }
+
+    /// Mark the latest successful claim of the red packet failed, same size so no storage is charged
+    pub fn internal_mark_claim_failed(&mut self, claimer_id: &AccountId, public_key: &PublicKey) {
+        let mut claims = match self.claims.get(claimer_id) {
+            Some(claims) => claims,
+            None => return
+        };
+        let index = (0..claims.len()).rev().find(|index| {
+            let record = claims.get(*index).unwrap();
+            record.success && &record.public_key == public_key
+        });
+        if let Some(index) = index {
+            let mut record = claims.get(index).unwrap();
+            record.success = false;
+            claims.replace(index, &record);
+        };
+    }
+
+    /// Remove all claims of the account
+    pub fn internal_clear_claims(&mut self, account_id: &AccountId) {
+        if let Some(mut claims) = self.claims.remove(account_id) {
+            claims.clear();
+        };
+    }
+}
@@ -3,8 +3,49 @@ use near_sdk::{Balance, Gas};
 
 pub const MAX_RED_PACKET_SPLIT: usize = 100;
 pub const MAX_RED_PACKET_MSG_LEN: usize = 100;
+// every non fungible token is refunded by one nft_transfer
+pub const MAX_NFT_RED_PACKET_SPLIT: usize = 10;
+// every bundle leg is claimed by one ft_transfer with resolver
+pub const MAX_BUNDLE_LEGS: usize = 5;
+// gas limit of one transaction, bounds the bundle legs of a claim
+pub const MAX_GAS_FOR_CLAIM: Gas = Gas(300_000_000_000_000);
+pub const RETRY_CLAIM_DURATION: u64 = 604_800_000_000_000; // 7 days
+// resolvers run within a few blocks, an older pending claim lost its resolver
+pub const PENDING_CLAIM_TIMEOUT: u64 = 86_400_000_000_000; // 1 day
+pub const MAX_WRONG_PASSWORD_ATTEMPTS: u32 = 3;
+pub const WRONG_PASSWORD_LOCK_DURATION: u64 = 600_000_000_000; // 10 minutes
+// claimer ids are chosen by the caller, bound the tracked ones
+pub const MAX_WRONG_PASSWORD_CLAIMERS: usize = 50;
+// page size of paginated views without `limit`
+pub const DEFAULT_VIEW_LIMIT: u64 = 20;
 
+// storage deposit reserved for a claim, the minimum of the token contract is attached when the claim resolves
+pub const FT_STORAGE_DEPOSIT: Balance = 1_250_000_000_000_000_000_000; // 0.00125 NEAR
 pub const ONE_YOCTO: Balance = 1;
+pub const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
 
+// expected gas of one claim with the access key, without the storage check and bundle legs
+pub const GAS_FOR_CLAIM_RED_PACKET: Gas = Gas(100_000_000_000_000);
+// 2x minimum gas price, covers pessimistic gas price of access key transactions
+pub const ACCESS_KEY_GAS_PRICE: Balance = 200_000_000;
 pub const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
-pub const GAS_FOR_RESOLVE_CLAIM_FUNGIBLE_TOKEN_RED_PACKET: Gas = Gas(20_000_000_000_000);
\ No newline at end of file
+pub const GAS_FOR_NFT_TRANSFER: Gas = Gas(15_000_000_000_000);
+pub const GAS_FOR_MT_TRANSFER: Gas = Gas(15_000_000_000_000);
+pub const GAS_FOR_RESOLVE_PAYOUT: Gas = Gas(10_000_000_000_000);
+pub const GAS_FOR_FT_STORAGE_BALANCE_OF: Gas = Gas(5_000_000_000_000);
+pub const GAS_FOR_FT_STORAGE_BALANCE_BOUNDS: Gas = Gas(5_000_000_000_000);
+pub const GAS_FOR_FT_STORAGE_DEPOSIT: Gas = Gas(10_000_000_000_000);
+// execution of a resolver itself, the gas of promises it may schedule is added on top
+pub const GAS_FOR_RESOLVE: Gas = Gas(10_000_000_000_000);
+// failed claims are refunded by payout
+pub const GAS_FOR_RESOLVE_CLAIM_FUNGIBLE_TOKEN_RED_PACKET: Gas = Gas(GAS_FOR_RESOLVE.0 + GAS_FOR_FT_TRANSFER.0 + GAS_FOR_RESOLVE_PAYOUT.0); // 30 TGas
+pub const GAS_FOR_RESOLVE_RETRY_CLAIM: Gas = Gas(GAS_FOR_RESOLVE.0 + GAS_FOR_FT_TRANSFER.0 + GAS_FOR_RESOLVE_PAYOUT.0); // 30 TGas
+pub const GAS_FOR_RESOLVE_CLAIM_NON_FUNGIBLE_TOKEN_RED_PACKET: Gas = Gas(GAS_FOR_RESOLVE.0 + GAS_FOR_NFT_TRANSFER.0 + GAS_FOR_RESOLVE_PAYOUT.0); // 35 TGas
+pub const GAS_FOR_RESOLVE_CLAIM_MULTI_TOKEN_RED_PACKET: Gas = Gas(GAS_FOR_RESOLVE.0 + GAS_FOR_MT_TRANSFER.0 + GAS_FOR_RESOLVE_PAYOUT.0); // 35 TGas
+// payout of the unused deposit, then storage deposit, transfer and resolve
+pub const GAS_FOR_RESOLVE_FT_STORAGE_BALANCE_OF_CLAIMER: Gas = Gas(
+    GAS_FOR_RESOLVE.0 + GAS_FOR_RESOLVE_PAYOUT.0 + GAS_FOR_FT_STORAGE_DEPOSIT.0 + GAS_FOR_FT_TRANSFER.0
+        + GAS_FOR_RESOLVE_CLAIM_FUNGIBLE_TOKEN_RED_PACKET.0
+); // 70 TGas
+// payouts of a forced unregister, the rest of 300 TGas is left for the unregister itself
+pub const MAX_GAS_FOR_FORCED_REFUNDS: Gas = Gas(200_000_000_000_000);
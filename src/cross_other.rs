@@ -2,12 +2,33 @@
 use near_sdk::{AccountId, ext_contract};
 use near_sdk::json_types::U128;
 use near_sdk::{Promise, PublicKey};
+use near_contract_standards::non_fungible_token::TokenId;
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
 
 
 #[ext_contract(ext_ft)]
 trait ExtFt {
     #[payable]
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance;
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance>;
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds;
+}
+
+#[ext_contract(ext_nft)]
+trait ExtNft {
+    #[payable]
+    fn nft_transfer(&mut self, receiver_id: AccountId, token_id: TokenId, approval_id: Option<u64>, memo: Option<String>);
+}
+
+#[ext_contract(ext_mt)]
+trait ExtMt {
+    #[payable]
+    fn mt_transfer(&mut self, receiver_id: AccountId, token_id: String, amount: U128, approval: Option<(AccountId, u64)>, memo: Option<String>);
 }
 
 #[ext_contract(ext_helper)]
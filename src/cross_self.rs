@@ -2,6 +2,8 @@
 use near_sdk::{AccountId, ext_contract};
 use near_sdk::json_types::U128;
 use near_sdk::PublicKey;
+use near_contract_standards::non_fungible_token::TokenId;
+use crate::enums::Token;
 
 
 #[ext_contract(ext_self)]
@@ -15,4 +17,63 @@ trait ExtSelf {
         token_id: AccountId,
         public_key: PublicKey
     );
+
+    #[private]
+    fn resolve_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token: Token,
+        token_id: Option<AccountId>,
+        amount: U128
+    );
+
+    #[private]
+    fn resolve_payout_nft(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: AccountId,
+        nft_token_ids: Vec<TokenId>
+    );
+
+    #[private]
+    fn resolve_retry_claim(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        amount: U128,
+        token_id: AccountId,
+        public_key: PublicKey
+    );
+
+    #[private]
+    fn resolve_ft_storage_balance_of_claimer(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        amount: U128,
+        token_id: AccountId,
+        public_key: PublicKey,
+        storage_deposit: U128
+    );
+
+    #[private]
+    fn resolve_claim_non_fungible_token_red_packet(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        nft_token_id: TokenId,
+        token_id: AccountId,
+        public_key: PublicKey
+    );
+
+    #[private]
+    fn resolve_claim_multi_token_red_packet(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        amount: U128,
+        token_id: AccountId,
+        mt_token_id: String,
+        public_key: PublicKey
+    );
 }
\ No newline at end of file
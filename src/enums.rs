@@ -1,25 +1,49 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Serialize,Deserialize};
 use near_sdk::BorshStorageKey;
+use near_sdk::json_types::U128;
 
 
 #[derive(BorshStorageKey,BorshSerialize)]
 pub enum StorageKey {
     RedPackets,
     Owners,
-    DynamicStorageManager
+    DynamicStorageManager,
+    Ledger,
+    Claims,
+    ApprovedFunders,
+    AccountClaims { account_hash: Vec<u8> },
+    Summaries
 }
 
-#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize,Clone,PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum Token {
     NEAR,
-    FungibleToken
+    FungibleToken,
+    NonFungibleToken,
+    /// NEP-245 token, `token_id` is the id inside the multi token contract
+    MultiToken {
+        token_id: String
+    }
 }
 
 #[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub enum SplitMod {
     Average,
-    Random
+    Random,
+    /// Explicit amount of every share, handed out in random order
+    Custom(Vec<U128>),
+    /// Random shares generated on creation, handed out in random order
+    RandomPrecomputed
+}
+
+#[derive(Serialize,Deserialize,PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RedPacketStatus {
+    Active,
+    Expired,
+    RunOut,
+    Refunded
 }
\ No newline at end of file
@@ -3,10 +3,29 @@ pub const ERR_02_NO_PERMISSION_TO_RED_PACKET: &str = "ERROR_02: No Permission to
 pub const ERR_03_RED_PACKET_NOT_RUN_OUT: &str = "ERROR_03: Red packet does not run out";
 pub const ERR_04_INVALID_PARAMETER: &str = "ERROR_04: Invalid red packet parameter exists";
 pub const ERR_05_NOT_UNIQUE_PUBLIC_KEY: &str = "ERROR_05: Red packet with the same public key exists";
+pub const ERR_06_RED_PACKET_EXPIRED: &str = "ERROR_06: Red packet is expired";
 pub const ERR_07_NO_DOUBLE_CLAIM: &str = "ERROR_07: No double claim";
 pub const ERR_08_CLAIMER_NOT_IN_WHITE_LIST: &str = "ERROR_08: Claimer is not in the white list of red packet";
 pub const ERR_09_CLAIM_FT_RED_PACKET_FAILED: &str = "ERROR_09: Failed to claim fungible token red packet";
+pub const ERR_10_RED_PACKET_NOT_OPEN: &str = "ERROR_10: Red packet is not open yet";
 
 pub const ERR_11_WRONG_RECEIVER_MESSAGE: &str = "ERROR_11: Wrong receiver message";
+pub const ERR_12_NOT_ENOUGH_RED_PACKET_BALANCE: &str = "ERROR_12: Not enough red packet balance";
+pub const ERR_13_RED_PACKET_RUN_OUT: &str = "ERROR_13: Red packet runs out";
+pub const ERR_14_CLAIM_NFT_RED_PACKET_FAILED: &str = "ERROR_14: Failed to claim non fungible token red packet";
+pub const ERR_15_CLAIM_MT_RED_PACKET_FAILED: &str = "ERROR_15: Failed to claim multi token red packet";
+pub const ERR_16_WRONG_PASSWORD: &str = "ERROR_16: Wrong red packet password";
+pub const ERR_17_TOO_MANY_WRONG_PASSWORD_ATTEMPTS: &str = "ERROR_17: Too many wrong password attempts, try again later";
+pub const ERR_18_NOT_ENOUGH_BALANCE_FOR_ALLOWANCE: &str = "ERROR_18: Not enough balance to pay access key allowance";
+pub const ERR_19_NO_RETRY_CLAIM: &str = "ERROR_19: No retryable claim";
+pub const ERR_20_RETRY_CLAIM_EXPIRED: &str = "ERROR_20: Retryable claim is expired";
+pub const ERR_21_RETRY_CLAIM_IN_PROGRESS: &str = "ERROR_21: Retryable claim is in progress";
+pub const ERR_22_NOT_ENOUGH_WITHDRAWABLE_BALANCE: &str = "ERROR_22: Not enough withdrawable balance";
+pub const ERR_23_RED_PACKET_HAS_PENDING_CLAIMS: &str = "ERROR_23: Red packet has pending claims";
+pub const ERR_25_FUNDER_NOT_APPROVED: &str = "ERROR_25: Funder is not approved by the owner";
+pub const ERR_26_DEPOSIT_TO_OTHER_ACCOUNT: &str = "ERROR_26: Only the depositor's own internal balance can be deposited into";
+pub const ERR_27_RED_PACKET_NOT_SEALED: &str = "ERROR_27: Red packet is not sealed yet";
+pub const ERR_28_RED_PACKET_SEALED: &str = "ERROR_28: Red packet is sealed, no more tokens can be added";
+pub const ERR_29_TOO_MANY_RED_PACKETS_TO_REFUND: &str = "ERROR_29: Too many live red packets to refund at once, refund some of them first";
 
 pub const ERR_24_NO_STORAGE_BALANCE_BOUNDS: &str = "ERROR_24: This contract does not have storage balance bounds. Depending on dynamic storage usage";
\ No newline at end of file
@@ -0,0 +1,87 @@
+//! NEP-297 events, logged as `EVENT_JSON:{"standard":"saika","version":"1.0.0","event":"claim","data":[...]}`
+
+use crate::enums::Token;
+use crate::red_packet::RedPacket;
+
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::{AccountId, env, PublicKey, serde_json};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+
+
+pub const EVENT_STANDARD: &str = "saika";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum EventKind {
+    Create,
+    TopUp,
+    AddBundleLeg,
+    TransferOwnership,
+    Claim,
+    ClaimFailed,
+    Refund,
+    RemoveHistory,
+    ClearHistory,
+    Deposit,
+    Credit,
+    Withdraw,
+    StorageDeposit,
+    StorageWithdraw,
+    Unregister
+}
+
+/// Fields not related to the event are omitted
+#[derive(Serialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EventData<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<&'a PublicKey>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<&'a Token>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<&'a AccountId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner_id: Option<&'a AccountId>,
+    /// Funder, claimer, refund operator, previous owner or storage account, depending on the event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<&'a AccountId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<U128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nft_token_id: Option<&'a TokenId>
+}
+
+impl<'a> EventData<'a> {
+    pub fn from_red_packet(public_key: &'a PublicKey, red_packet: &'a RedPacket) -> Self {
+        Self {
+            public_key: Some(public_key),
+            token: Some(&red_packet.token),
+            token_id: red_packet.token_id.as_ref(),
+            owner_id: Some(&red_packet.owner_id),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    event: EventKind,
+    data: Vec<EventData<'a>>
+}
+
+pub fn emit_event(event: EventKind, data: Vec<EventData>) {
+    let event_log = EventLog {
+        standard: EVENT_STANDARD,
+        version: EVENT_STANDARD_VERSION,
+        event,
+        data
+    };
+    env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(&event_log).unwrap()));
+}
@@ -1,11 +1,12 @@
 use crate::Contract;
 use crate::ContractExt;
 use crate::errors::*;
-use crate::enums::SplitMod;
+use crate::enums::{SplitMod, Token};
+use crate::ledger::WithdrawableBalance;
+use crate::red_packet_options::RedPacketOptions;
 
-use std::collections::HashSet;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
-use near_sdk::{AccountId, PromiseOrValue, near_bindgen, serde_json, env, PublicKey};
+use near_sdk::{AccountId, PromiseOrValue, near_bindgen, serde_json, env, PublicKey, require};
 use near_sdk::json_types::U128;
 use near_sdk::serde::Deserialize;
 
@@ -23,20 +24,58 @@ impl FungibleTokenReceiver for Contract {
                     public_key,
                     split,
                     split_mod,
-                    msg,
-                    white_list
+                    options
                 } => {
                     self.internal_create_fungible_token_red_packet(
-                        env::predecessor_account_id(),
+                        WithdrawableBalance {
+                            token: Token::FungibleToken,
+                            token_id: Some(env::predecessor_account_id()),
+                            amount,
+                            nft_token_id: None
+                        },
                         sender_id,
-                        amount,
                         public_key,
                         split,
                         split_mod,
-                        msg,
-                        white_list
+                        options
+                    )
+                },
+                ReceiverMessage::FungibleTokenRedPacketTopUp {
+                    public_key,
+                    extra_split
+                } => {
+                    self.internal_top_up_red_packet(
+                        Token::FungibleToken,
+                        Some(env::predecessor_account_id()),
+                        sender_id,
+                        amount,
+                        public_key,
+                        extra_split
                     )
-                }
+                },
+                ReceiverMessage::FungibleTokenBundleLeg {
+                    public_key,
+                    leg_split_mod
+                } => {
+                    self.internal_add_bundle_leg(
+                        env::predecessor_account_id(),
+                        sender_id,
+                        amount,
+                        public_key,
+                        leg_split_mod
+                    )
+                },
+                ReceiverMessage::Deposit { account_id } => {
+                    require!(account_id == sender_id, ERR_26_DEPOSIT_TO_OTHER_ACCOUNT);
+                    self.internal_deposit(
+                        account_id,
+                        Token::FungibleToken,
+                        Some(env::predecessor_account_id()),
+                        amount
+                    );
+                    PromiseOrValue::Value(U128(0))
+                },
+                _ => panic!("{}", ERR_11_WRONG_RECEIVER_MESSAGE)
             }
         }
     }
@@ -45,11 +84,116 @@ impl FungibleTokenReceiver for Contract {
 #[derive(Deserialize)]
 #[serde(crate = "near_sdk::serde", untagged)]
 pub enum ReceiverMessage {
+    /// Also used by multi token red packet
     FungibleTokenRedPacket {
         public_key: PublicKey,
         split: usize,
         split_mod: SplitMod,
-        msg: Option<String>,
-        white_list: Option<HashSet<AccountId>>
+        #[serde(flatten)]
+        options: RedPacketOptions
+    },
+    FungibleTokenRedPacketTopUp {
+        public_key: PublicKey,
+        extra_split: usize
+    },
+    /// Add an extra fungible token to the unsealed red packet of the same public key
+    FungibleTokenBundleLeg {
+        public_key: PublicKey,
+        leg_split_mod: SplitMod
+    },
+    /// Send non fungible tokens one by one with the same message to fill the red packet,
+    /// `seal` of the options seals it with the sent token
+    NonFungibleTokenRedPacket {
+        public_key: PublicKey,
+        #[serde(flatten)]
+        options: RedPacketOptions
+    },
+    /// Deposit into the internal balance of the registered sender, `account_id` must be the sender,
+    /// also used by multi token.
+    /// Untagged variants are tried in order, this one matches almost any object so it stays last.
+    Deposit {
+        account_id: AccountId
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReceiverMessage;
+    use near_sdk::serde_json;
+
+    const PUBLIC_KEY: &str = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp";
+
+    fn parse(msg: String) -> ReceiverMessage {
+        serde_json::from_str::<ReceiverMessage>(&msg).unwrap()
+    }
+
+    #[test]
+    fn parse_fungible_token_red_packet() {
+        let msg = format!(r#"{{"public_key":"{}","split":2,"split_mod":"Average","owner_id":"alice.near"}}"#, PUBLIC_KEY);
+        assert!(matches!(parse(msg), ReceiverMessage::FungibleTokenRedPacket { split: 2, .. }));
+    }
+
+    #[test]
+    fn parse_fungible_token_red_packet_top_up() {
+        let msg = format!(r#"{{"public_key":"{}","extra_split":3}}"#, PUBLIC_KEY);
+        assert!(matches!(parse(msg), ReceiverMessage::FungibleTokenRedPacketTopUp { extra_split: 3, .. }));
+    }
+
+    #[test]
+    fn parse_fungible_token_bundle_leg() {
+        let msg = format!(r#"{{"public_key":"{}","leg_split_mod":"Random"}}"#, PUBLIC_KEY);
+        assert!(matches!(parse(msg), ReceiverMessage::FungibleTokenBundleLeg { .. }));
+    }
+
+    #[test]
+    fn parse_non_fungible_token_red_packet() {
+        let msg = format!(r#"{{"public_key":"{}","seal":true}}"#, PUBLIC_KEY);
+        match parse(msg) {
+            ReceiverMessage::NonFungibleTokenRedPacket { options, .. } => assert_eq!(options.seal, Some(true)),
+            _ => panic!("wrong message")
+        };
+        let msg = format!(r#"{{"public_key":"{}"}}"#, PUBLIC_KEY);
+        match parse(msg) {
+            ReceiverMessage::NonFungibleTokenRedPacket { options, .. } => assert_eq!(options.seal, None),
+            _ => panic!("wrong message")
+        };
+    }
+
+    #[test]
+    fn parse_flattened_options() {
+        let msg = format!(
+            r#"{{"public_key":"{}","split":2,"split_mod":"Average","owner_id":"alice.near","expire_at":"100","retryable_claims":true}}"#,
+            PUBLIC_KEY
+        );
+        match parse(msg) {
+            ReceiverMessage::FungibleTokenRedPacket { options, .. } => {
+                assert_eq!(options.owner_id.unwrap().as_str(), "alice.near");
+                assert_eq!(options.expire_at.unwrap().0, 100);
+                assert_eq!(options.retryable_claims, Some(true));
+            },
+            _ => panic!("wrong message")
+        };
+        let msg = format!(r#"{{"public_key":"{}","seal":true,"msg":"hi"}}"#, PUBLIC_KEY);
+        match parse(msg) {
+            ReceiverMessage::NonFungibleTokenRedPacket { options, .. } => {
+                assert_eq!(options.seal, Some(true));
+                assert_eq!(options.msg.as_deref(), Some("hi"));
+            },
+            _ => panic!("wrong message")
+        };
+    }
+
+    #[test]
+    fn parse_deposit() {
+        let msg = r#"{"account_id":"alice.near"}"#.to_string();
+        assert!(matches!(parse(msg), ReceiverMessage::Deposit { .. }));
+    }
+
+    #[test]
+    fn malformed_message_is_not_deposit() {
+        // missing split_mod
+        let msg = format!(r#"{{"public_key":"{}","split":2}}"#, PUBLIC_KEY);
+        assert!(!matches!(parse(msg), ReceiverMessage::Deposit { .. }));
+        assert!(serde_json::from_str::<ReceiverMessage>("{}").is_err());
     }
 }
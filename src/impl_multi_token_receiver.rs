@@ -0,0 +1,88 @@
+use crate::Contract;
+use crate::ContractExt;
+use crate::errors::*;
+use crate::enums::Token;
+use crate::impl_fungible_token_receiver::ReceiverMessage;
+use crate::ledger::WithdrawableBalance;
+use crate::multi_token_receiver::MultiTokenReceiver;
+
+use near_sdk::{AccountId, PromiseOrValue, near_bindgen, serde_json, env, require};
+use near_sdk::json_types::U128;
+
+
+#[near_bindgen]
+impl MultiTokenReceiver for Contract {
+    #[allow(unused_variables)]
+    fn mt_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_ids: Vec<AccountId>,
+        token_ids: Vec<String>,
+        amounts: Vec<U128>,
+        msg: String
+    ) -> PromiseOrValue<Vec<U128>> {
+        if msg.is_empty() {
+            PromiseOrValue::Value(amounts)
+        } else {
+            require!(
+                token_ids.len() == 1 && amounts.len() == 1 && previous_owner_ids.len() == 1,
+                ERR_04_INVALID_PARAMETER
+            );
+            let token = Token::MultiToken { token_id: token_ids[0].clone() };
+            let previous_owner_id = previous_owner_ids[0].clone();
+            let amount = amounts[0];
+            let receiver_message = serde_json::from_str::<ReceiverMessage>(msg.as_str())
+                .expect(ERR_11_WRONG_RECEIVER_MESSAGE);
+            let unused_amount = match receiver_message {
+                ReceiverMessage::FungibleTokenRedPacket {
+                    public_key,
+                    split,
+                    split_mod,
+                    options
+                } => {
+                    self.internal_create_fungible_token_red_packet(
+                        WithdrawableBalance {
+                            token,
+                            token_id: Some(env::predecessor_account_id()),
+                            amount,
+                            nft_token_id: None
+                        },
+                        previous_owner_id,
+                        public_key,
+                        split,
+                        split_mod,
+                        options
+                    )
+                },
+                ReceiverMessage::FungibleTokenRedPacketTopUp {
+                    public_key,
+                    extra_split
+                } => {
+                    self.internal_top_up_red_packet(
+                        token,
+                        Some(env::predecessor_account_id()),
+                        previous_owner_id,
+                        amount,
+                        public_key,
+                        extra_split
+                    )
+                },
+                ReceiverMessage::Deposit { account_id } => {
+                    require!(account_id == previous_owner_id, ERR_26_DEPOSIT_TO_OTHER_ACCOUNT);
+                    self.internal_deposit(
+                        account_id,
+                        token,
+                        Some(env::predecessor_account_id()),
+                        amount
+                    );
+                    PromiseOrValue::Value(U128(0))
+                },
+                _ => panic!("{}", ERR_11_WRONG_RECEIVER_MESSAGE)
+            };
+            match unused_amount {
+                PromiseOrValue::Value(unused_amount) => PromiseOrValue::Value(vec![unused_amount]),
+                PromiseOrValue::Promise(promise) => PromiseOrValue::Promise(promise)
+            }
+        }
+    }
+}
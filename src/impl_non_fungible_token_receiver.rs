@@ -0,0 +1,43 @@
+use crate::Contract;
+use crate::ContractExt;
+use crate::errors::*;
+use crate::impl_fungible_token_receiver::ReceiverMessage;
+
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::{AccountId, PromiseOrValue, near_bindgen, serde_json, env};
+
+
+#[near_bindgen]
+impl NonFungibleTokenReceiver for Contract {
+    #[allow(unused_variables)]
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String
+    ) -> PromiseOrValue<bool> {
+        if msg.is_empty() {
+            PromiseOrValue::Value(true)
+        } else {
+            let receiver_message = serde_json::from_str::<ReceiverMessage>(msg.as_str())
+                .expect(ERR_11_WRONG_RECEIVER_MESSAGE);
+            match receiver_message {
+                ReceiverMessage::NonFungibleTokenRedPacket {
+                    public_key,
+                    options
+                } => {
+                    self.internal_create_or_add_non_fungible_token_red_packet(
+                        env::predecessor_account_id(),
+                        previous_owner_id,
+                        token_id,
+                        public_key,
+                        options
+                    )
+                },
+                _ => panic!("{}", ERR_11_WRONG_RECEIVER_MESSAGE)
+            }
+        }
+    }
+}
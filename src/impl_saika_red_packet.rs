@@ -1,59 +1,135 @@
 use crate::enums::*;
+use crate::constants::{DEFAULT_VIEW_LIMIT, MAX_GAS_FOR_FORCED_REFUNDS, MAX_NFT_RED_PACKET_SPLIT};
 use crate::utils::*;
 use crate::dynamic_storage_management::{DynamicStorageBasic, DynamicStorageCore};
 use crate::errors::*;
 use crate::red_packet::RedPacket;
+use crate::red_packet_summary::RedPacketSummary;
+use crate::red_packet_options::RedPacketOptions;
 use crate::Contract;
 use crate::ContractExt;
-use crate::red_packet_view::{parse_red_packet_view, RedPacketView};
+use crate::red_packet_view::{parse_red_packet_summary_view, parse_red_packet_view, RedPacketFilter, RedPacketSummaryView, RedPacketView};
 use crate::saika_red_packet::SaikaRedPacket;
 use crate::cross_other::*;
+use crate::white_list::MerkleProof;
+use crate::events::{emit_event, EventData, EventKind};
+use crate::ledger::WithdrawableBalance;
+use crate::claim_index::ClaimRecord;
 
 use std::collections::HashSet;
-use near_sdk::{AccountId, env, near_bindgen, PublicKey, PromiseOrValue, require, Balance, Promise};
-use near_sdk::json_types::{U128};
-
-const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::{AccountId, env, log, near_bindgen, PublicKey, PromiseOrValue, require, Balance, Promise, assert_one_yocto};
+use near_sdk::json_types::U128;
 
 #[near_bindgen]
 impl SaikaRedPacket for Contract {
-    /// create a near red packet
+    /// create a near red packet, owned by the caller or the given owner.
+    /// Storage is always charged to the owner, who can refund and remove the red packet.
     #[payable]
     fn create_near_red_packet(
         &mut self,
         public_key: PublicKey,
         split: usize,
         split_mod: SplitMod,
-        msg: Option<String>,
-        white_list: Option<HashSet<AccountId>>
+        options: Option<RedPacketOptions>
     ) {
         self.internal_create_near_red_packet(
             env::predecessor_account_id(),
             env::attached_deposit(),
-            public_key.clone(),
+            public_key,
             split,
             split_mod,
-            msg,
-            white_list
+            options.unwrap_or_default()
         );
-
-        // used for near official linkdrop
-        Promise::new(env::current_account_id())
-            .add_access_key(
-                public_key,
-                250_000_000_000_000_000_000_000,
-                env::current_account_id(),
-                "create_account_and_claim".into()
-            );
+    }
+    /// add balance and splits to a live near red packet
+    #[payable]
+    fn top_up_red_packet(&mut self, public_key: PublicKey, extra_split: usize) {
+        self.internal_top_up_red_packet(
+            Token::NEAR,
+            None,
+            env::predecessor_account_id(),
+            env::attached_deposit().into(),
+            public_key,
+            extra_split
+        );
+    }
+    /// open a non fungible token or bundle red packet for claims after all tokens arrived
+    fn seal_red_packet(&mut self, public_key: PublicKey) {
+        self.internal_seal_red_packet(public_key)
     }
     /// claim near red Packet and fungible token red packet with private key
-    fn claim_red_packet(&mut self, claimer_id: AccountId) -> U128 {
-        self.internal_claim_red_packet(claimer_id, false)
+    fn claim_red_packet(
+        &mut self,
+        claimer_id: AccountId,
+        merkle_proof: Option<MerkleProof>,
+        password: Option<String>
+    ) -> U128 {
+        self.internal_claim_red_packet(claimer_id, merkle_proof, password, false)
     }
-    /// refund balance
+    /// refund balance, anyone can refund an expired red packet to its owner
     fn refund(&mut self, public_key: PublicKey) -> U128 {
         self.internal_refund(public_key)
     }
+    /// claimer retries an escrowed failed claim after registering with the token
+    fn retry_claim(&mut self, public_key: PublicKey) -> U128 {
+        self.internal_retry_claim(public_key)
+    }
+    /// anyone can return escrowed claims past the deadline to the owner
+    fn refund_expired_retry_claims(&mut self, public_key: PublicKey) -> U128 {
+        self.internal_refund_expired_retry_claims(public_key)
+    }
+    /// deposit near into the internal balance of the registered caller
+    #[payable]
+    fn deposit(&mut self) {
+        self.internal_deposit(
+            env::predecessor_account_id(),
+            Token::NEAR,
+            None,
+            env::attached_deposit().into()
+        );
+    }
+    /// create near, fungible token or multi token red packet from a withdrawable balance of the caller,
+    /// near red packet pays the access key allowance from `amount`
+    fn create_red_packet_from_balance(
+        &mut self,
+        balance: WithdrawableBalance,
+        public_key: PublicKey,
+        split: usize,
+        split_mod: SplitMod,
+        options: Option<RedPacketOptions>
+    ) {
+        self.internal_create_red_packet_from_balance(
+            balance,
+            public_key,
+            split,
+            split_mod,
+            options.unwrap_or_default()
+        )
+    }
+    /// withdraw deposits and failed payouts from the internal balance
+    #[payable]
+    fn withdraw(&mut self, token: Token, token_id: Option<AccountId>, amount: Option<U128>) -> U128 {
+        self.internal_withdraw(token, token_id, amount)
+    }
+    /// withdraw a non fungible token of a failed payout from the internal balance
+    #[payable]
+    fn withdraw_nft(&mut self, token_id: AccountId, nft_token_id: TokenId) {
+        self.internal_withdraw_nft(token_id, nft_token_id)
+    }
+    /// transfer red packet to another registered account which approved the owner as a funder, storage usage is moved to the new owner
+    #[payable]
+    fn transfer_red_packet_ownership(&mut self, public_key: PublicKey, new_owner_id: AccountId) {
+        self.internal_transfer_red_packet_ownership(public_key, new_owner_id)
+    }
+    /// allow funder to create red packets owned by the caller, storage is charged to the caller
+    fn approve_funder(&mut self, funder_id: AccountId) {
+        self.internal_approve_funder(funder_id)
+    }
+    /// disallow funder to create red packets owned by the caller
+    fn revoke_funder(&mut self, funder_id: AccountId) {
+        self.internal_revoke_funder(funder_id)
+    }
     /// remove red packet run out
     fn remove_history(&mut self, public_key: PublicKey) {
         self.internal_remove_history(public_key)
@@ -62,6 +138,10 @@ impl SaikaRedPacket for Contract {
     fn clear_history(&mut self) {
         self.internal_clear_history();
     }
+    /// drop pending claims older than 1 day, so the red packet can be removed
+    fn clear_stale_pending_claims(&mut self, public_key: PublicKey) -> usize {
+        self.internal_clear_stale_pending_claims(public_key)
+    }
     /// view owner's red packets detail
     fn get_red_packets_by_owner_id(&self, owner_id: AccountId) -> Vec<RedPacketView> {
         self.owners.get(&owner_id)
@@ -75,17 +155,67 @@ impl SaikaRedPacket for Contract {
             })
             .collect()
     }
+    /// view owner's red packets in public key order, filtered before pagination
+    fn get_red_packets_by_owner_id_paginated(
+        &self,
+        owner_id: AccountId,
+        filter: Option<RedPacketFilter>,
+        from_index: Option<u64>,
+        limit: Option<u64>
+    ) -> Vec<RedPacketView> {
+        self.filter_red_packets_by_owner_id(&owner_id, filter, from_index, limit)
+            .into_iter()
+            .map(|(public_key, _)| {
+                let red_packet = self.red_packets.get(&public_key).unwrap();
+                parse_red_packet_view(red_packet, public_key)
+            })
+            .collect()
+    }
+    /// same as `get_red_packets_by_owner_id_paginated` without per account records
+    fn get_red_packet_summaries_by_owner_id(
+        &self,
+        owner_id: AccountId,
+        filter: Option<RedPacketFilter>,
+        from_index: Option<u64>,
+        limit: Option<u64>
+    ) -> Vec<RedPacketSummaryView> {
+        self.filter_red_packets_by_owner_id(&owner_id, filter, from_index, limit)
+            .into_iter()
+            .map(|(public_key, summary)| parse_red_packet_summary_view(summary, public_key))
+            .collect()
+    }
     /// view owner's red packet public keys
     fn get_pks_by_owner_id(&self, owner_id: AccountId) -> HashSet<PublicKey> {
         self.owners.get(&owner_id).unwrap_or(HashSet::new())
     }
+    /// view accounts allowed to create red packets owned by owner
+    fn get_approved_funders(&self, owner_id: AccountId) -> HashSet<AccountId> {
+        self.approved_funders.get(&owner_id).unwrap_or_default()
+    }
     /// view the red packet detail related to public key
     fn get_red_packet_by_pk(&self, public_key: PublicKey) -> Option<RedPacketView> {
         let red_packet = self.red_packets.get(&public_key)?;
         Some(parse_red_packet_view(red_packet, public_key))
     }
 
-    /// used for near official linkdrop
+    /// view deposits and failed payouts kept in the internal balance of the account
+    fn get_withdrawable_balances(&self, account_id: AccountId) -> Vec<WithdrawableBalance> {
+        self.ledger.get(&account_id).unwrap_or_default()
+    }
+
+    /// view claims of a registered claimer in claim order
+    fn get_claims_by_account(&self, account_id: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<ClaimRecord> {
+        let claims = match self.claims.get(&account_id) {
+            Some(claims) => claims,
+            None => return vec![]
+        };
+        let from_index = from_index.unwrap_or(0);
+        let to_index = std::cmp::min(from_index.saturating_add(limit.unwrap_or(DEFAULT_VIEW_LIMIT)), claims.len());
+        (from_index..to_index)
+            .map(|index| claims.get(index).unwrap())
+            .collect()
+    }
+
     fn get_key_balance(&self, key: PublicKey) -> U128 {
         let red_packet = self.red_packets
             .get(&key)
@@ -95,13 +225,13 @@ impl SaikaRedPacket for Contract {
         }
         match red_packet.token {
             Token::NEAR => red_packet.current_balance,
-            Token::FungibleToken => unimplemented!("This method only support native NEAR")
+            Token::FungibleToken | Token::NonFungibleToken | Token::MultiToken { .. } => unimplemented!("This method only support native NEAR")
         }
     }
 
     /// used for near official linkdrop
     fn create_account_and_claim(&mut self, new_account_id: AccountId, new_public_key: PublicKey) -> Promise {
-        let claim_amount = self.internal_claim_red_packet(new_account_id.clone(), true);
+        let claim_amount = self.internal_claim_red_packet(new_account_id.clone(), None, None, true);
         if claim_amount.0 == 0 {
             panic!("Red Packet is run out");
         }
@@ -113,86 +243,368 @@ impl SaikaRedPacket for Contract {
 
 
 impl Contract {
+    /// Create near red packet, the access key allowance is paid from `amount`
     pub fn internal_create_near_red_packet(
         &mut self,
-        owner_id: AccountId,
+        funder_id: AccountId,
         amount: Balance,
         public_key: PublicKey,
         split: usize,
         split_mod: SplitMod,
-        msg: Option<String>,
-        white_list: Option<HashSet<AccountId>>
+        options: RedPacketOptions
     ) {
+        let owner_id = options.owner_id(&funder_id);
+        let allowance = options.allowance
+            .map(|allowance| allowance.0)
+            .unwrap_or(default_access_key_allowance(&Token::NEAR, split));
+        let amount = amount.checked_sub(allowance).expect(ERR_18_NOT_ENOUGH_BALANCE_FOR_ALLOWANCE);
         self.assert_before_creation(amount, &public_key, &owner_id);
+        self.assert_approved_funder(&owner_id, &funder_id);
 
-        let near_red_packet = RedPacket::new_valid(
+        let mut near_red_packet = RedPacket::new_valid(
             Token::NEAR,
             None,
-            owner_id.clone(),
+            funder_id,
             amount.into(),
             split,
             split_mod,
-            msg,
-            white_list
+            options
         ).unwrap();
+        near_red_packet.access_key_allowance = U128(allowance);
+
+        emit_event(EventKind::Create, vec![EventData {
+            account_id: Some(&near_red_packet.funder_id),
+            amount: Some(near_red_packet.init_balance),
+            split: Some(near_red_packet.init_split),
+            ..EventData::from_red_packet(&public_key, &near_red_packet)
+        }]);
 
         self.measure_start();
-        self.add_red_packet(owner_id.clone(), public_key, near_red_packet);
+        self.add_red_packet(owner_id.clone(), public_key.clone(), near_red_packet);
         self.measure_end(&owner_id);
 
+        // create_account_and_claim is used for near official linkdrop
+        add_access_key(public_key, allowance, access_key_method_names(&Token::NEAR));
+
         self.assert_after_creation(&owner_id);
     }
 
+    /// Create fungible token or multi token red packet
     pub fn internal_create_fungible_token_red_packet(
         &mut self,
-        token_id: AccountId,
-        owner_id: AccountId,
-        amount: U128,
+        balance: WithdrawableBalance,
+        funder_id: AccountId,
         public_key: PublicKey,
         split: usize,
         split_mod: SplitMod,
-        msg: Option<String>,
-        white_list: Option<HashSet<AccountId>>
+        options: RedPacketOptions
     ) -> PromiseOrValue<U128> {
-        self.assert_before_creation(amount.0, &public_key, &owner_id);
+        let owner_id = options.owner_id(&funder_id);
+        self.assert_before_creation(balance.amount.0, &public_key, &owner_id);
+        self.assert_approved_funder(&owner_id, &funder_id);
 
-        let ft_red_packet = RedPacket::new_valid(
-            Token::FungibleToken,
-            Some(token_id),
-            owner_id.clone(),
-            amount,
+        let allowance = options.allowance
+            .map(|allowance| allowance.0)
+            .unwrap_or(default_access_key_allowance(&balance.token, split));
+        let mut ft_red_packet = RedPacket::new_valid(
+            balance.token,
+            balance.token_id,
+            funder_id,
+            balance.amount,
             split,
             split_mod,
-            msg,
-            white_list,
+            options
+        ).unwrap();
+        ft_red_packet.access_key_allowance = U128(allowance);
+        let ft_storage_reserve = ft_red_packet.ft_storage_reserve;
+
+        emit_event(EventKind::Create, vec![EventData {
+            account_id: Some(&ft_red_packet.funder_id),
+            amount: Some(ft_red_packet.init_balance),
+            split: Some(ft_red_packet.init_split),
+            ..EventData::from_red_packet(&public_key, &ft_red_packet)
+        }]);
+
+        self.measure_start();
+        self.add_red_packet(owner_id.clone(), public_key.clone(), ft_red_packet);
+        self.measure_end(&owner_id);
+
+        self.pay_from_storage_balance(&owner_id, allowance + ft_storage_reserve.0);
+        add_access_key(public_key, allowance, access_key_method_names(&Token::FungibleToken));
+
+        self.assert_after_creation(&owner_id);
+
+        PromiseOrValue::Value(U128(0))
+    }
+
+    /// Create non fungible token red packet with the first token,
+    /// the following tokens sent with the same public key are added to it
+    pub fn internal_create_or_add_non_fungible_token_red_packet(
+        &mut self,
+        token_id: AccountId,
+        funder_id: AccountId,
+        nft_token_id: TokenId,
+        public_key: PublicKey,
+        options: RedPacketOptions
+    ) -> PromiseOrValue<bool> {
+        if let Some(mut red_packet) = self.red_packets.get(&public_key) {
+            require!(
+                matches!(red_packet.token, Token::NonFungibleToken) && red_packet.token_id == Some(token_id),
+                ERR_04_INVALID_PARAMETER
+            );
+            red_packet.virtual_add_nft(funder_id.clone(), nft_token_id).unwrap();
+            if options.seal.unwrap_or(false) {
+                red_packet.virtual_seal(funder_id).unwrap();
+            };
+            let owner_id = red_packet.owner_id.clone();
+
+            emit_event(EventKind::TopUp, vec![EventData {
+                amount: Some(U128(1)),
+                split: Some(1),
+                nft_token_id: red_packet.nft_token_ids.last(),
+                ..EventData::from_red_packet(&public_key, &red_packet)
+            }]);
+
+            self.measure_start();
+            self.save_red_packet(&public_key, &red_packet);
+            self.measure_end(&owner_id);
+
+            self.assert_after_creation(&owner_id);
+
+            return PromiseOrValue::Value(false);
+        };
+
+        let owner_id = options.owner_id(&funder_id);
+        self.assert_before_creation(1, &public_key, &owner_id);
+        self.assert_approved_funder(&owner_id, &funder_id);
+
+        // more tokens are added later
+        let allowance = options.allowance
+            .map(|allowance| allowance.0)
+            .unwrap_or(default_access_key_allowance(&Token::NonFungibleToken, MAX_NFT_RED_PACKET_SPLIT));
+        let mut nft_red_packet = RedPacket::new_valid_non_fungible_token(
+            token_id,
+            funder_id,
+            nft_token_id,
+            options
         ).unwrap();
+        nft_red_packet.access_key_allowance = U128(allowance);
+
+        emit_event(EventKind::Create, vec![EventData {
+            account_id: Some(&nft_red_packet.funder_id),
+            amount: Some(nft_red_packet.init_balance),
+            split: Some(nft_red_packet.init_split),
+            nft_token_id: nft_red_packet.nft_token_ids.last(),
+            ..EventData::from_red_packet(&public_key, &nft_red_packet)
+        }]);
+
+        self.measure_start();
+        self.add_red_packet(owner_id.clone(), public_key.clone(), nft_red_packet);
+        self.measure_end(&owner_id);
+
+        self.pay_from_storage_balance(&owner_id, allowance);
+        add_access_key(public_key, allowance, access_key_method_names(&Token::NonFungibleToken));
+
+        self.assert_after_creation(&owner_id);
+
+        PromiseOrValue::Value(false)
+    }
+
+    pub fn internal_top_up_red_packet(
+        &mut self,
+        token: Token,
+        token_id: Option<AccountId>,
+        owner_id: AccountId,
+        amount: U128,
+        public_key: PublicKey,
+        extra_split: usize
+    ) -> PromiseOrValue<U128> {
+        assert_zero_deposit(amount.0);
+
+        let mut red_packet = self.red_packets
+            .get(&public_key)
+            .expect(ERR_01_NO_MATCHING_RED_PACKET);
+        require!(red_packet.token == token && red_packet.token_id == token_id, ERR_04_INVALID_PARAMETER);
+        // extra splits replace the access key with a new one for all the remaining splits,
+        // paid in full from the deposit for NEAR, else from the storage balance,
+        // the allowance left on the old key is unknown to the contract and stays with it
+        let allowance = if extra_split != 0 {
+            red_packet.access_key_allowance_per_claim() * u128::try_from(red_packet.current_split + extra_split).unwrap()
+        } else {
+            0
+        };
+        let amount = match token {
+            Token::NEAR => U128(amount.0.checked_sub(allowance).expect(ERR_18_NOT_ENOUGH_BALANCE_FOR_ALLOWANCE)),
+            _ => amount
+        };
+        red_packet.virtual_top_up(owner_id.clone(), amount, extra_split).unwrap();
+        if allowance != 0 {
+            red_packet.access_key_allowance = U128(allowance);
+        };
+
+        emit_event(EventKind::TopUp, vec![EventData {
+            amount: Some(amount),
+            split: Some(extra_split),
+            ..EventData::from_red_packet(&public_key, &red_packet)
+        }]);
 
         self.measure_start();
-        self.add_red_packet(owner_id.clone(), public_key, ft_red_packet);
+        self.save_red_packet(&public_key, &red_packet);
         self.measure_end(&owner_id);
 
+        if allowance != 0 {
+            if !matches!(token, Token::NEAR) {
+                self.pay_from_storage_balance(&owner_id, allowance);
+            };
+            reset_access_key(public_key, allowance, access_key_method_names(&token));
+        };
+
         self.assert_after_creation(&owner_id);
 
         PromiseOrValue::Value(U128(0))
     }
 
-    pub fn internal_claim_red_packet(&mut self, claimer_id: AccountId, create: bool) -> U128 {
+    pub fn internal_seal_red_packet(&mut self, public_key: PublicKey) {
+        let mut red_packet = self.red_packets
+            .get(&public_key)
+            .expect(ERR_01_NO_MATCHING_RED_PACKET);
+        red_packet.virtual_seal(env::predecessor_account_id()).unwrap();
+        log!("Seal red packet, split: {}", red_packet.init_split);
+
+        self.save_red_packet(&public_key, &red_packet);
+    }
+
+    pub fn internal_add_bundle_leg(
+        &mut self,
+        token_id: AccountId,
+        operator_id: AccountId,
+        amount: U128,
+        public_key: PublicKey,
+        split_mod: SplitMod
+    ) -> PromiseOrValue<U128> {
+        assert_zero_deposit(amount.0);
+
+        let mut red_packet = self.red_packets
+            .get(&public_key)
+            .expect(ERR_01_NO_MATCHING_RED_PACKET);
+        red_packet.virtual_add_bundle_leg(operator_id, token_id.clone(), amount, split_mod).unwrap();
+        let owner_id = red_packet.owner_id.clone();
+        // every claim pays one more transfer with resolver, a new access key covers the remaining splits
+        let allowance = red_packet.access_key_allowance_per_claim() * u128::try_from(red_packet.current_split).unwrap();
+        red_packet.access_key_allowance = U128(allowance);
+
+        emit_event(EventKind::AddBundleLeg, vec![EventData {
+            token: Some(&Token::FungibleToken),
+            token_id: Some(&token_id),
+            amount: Some(amount),
+            ..EventData::from_red_packet(&public_key, &red_packet)
+        }]);
+
+        self.measure_start();
+        self.save_red_packet(&public_key, &red_packet);
+        self.measure_end(&owner_id);
+
+        self.pay_from_storage_balance(&owner_id, allowance);
+        reset_access_key(public_key, allowance, access_key_method_names(&red_packet.token));
+
+        self.assert_after_creation(&owner_id);
+
+        PromiseOrValue::Value(U128(0))
+    }
+
+    pub fn internal_claim_red_packet(
+        &mut self,
+        claimer_id: AccountId,
+        merkle_proof: Option<MerkleProof>,
+        password: Option<String>,
+        create: bool
+    ) -> U128 {
         let public_key = env::signer_account_pk();
         let mut red_packet = self.red_packets
             .get(&public_key)
             .expect(ERR_01_NO_MATCHING_RED_PACKET);
 
-        let min_sub = match red_packet.token {
-            Token::NEAR => Some(ONE_NEAR / 10), // 0.1 NEAR
-            Token::FungibleToken => None
+        if red_packet.is_password_protected() && create {
+            panic!("Password protected red packet can not be used to create account")
+        };
+        if !red_packet.virtual_check_password(&claimer_id, password).unwrap() {
+            // keep the wrong attempt, panic would revert it
+            log!("{}", ERR_16_WRONG_PASSWORD);
+            self.measure_start();
+            self.save_red_packet(&public_key, &red_packet);
+            self.measure_end(&red_packet.owner_id);
+            return U128(0);
         };
 
-        let claim_amount = red_packet.virtual_claim(claimer_id.clone(), min_sub).unwrap();
+        let min_sub = red_packet.min_sub();
+
+        let claim_amount = red_packet.virtual_claim(claimer_id.clone(), min_sub, merkle_proof).unwrap();
+        let leftover = red_packet.virtual_refund_leftover();
+        if claim_amount.0 != 0 {
+            if !matches!(red_packet.token, Token::NEAR) {
+                red_packet.add_pending_claim(claimer_id.clone(), red_packet.token_id.clone().unwrap(), claim_amount);
+            };
+            let leg_claims: Vec<(AccountId, U128)> = red_packet.bundle
+                .iter()
+                .filter_map(|leg| leg.claimers.get(&claimer_id).map(|amount| (leg.token_id.clone(), *amount)))
+                .filter(|(_, amount)| amount.0 != 0)
+                .collect();
+            for (token_id, amount) in leg_claims {
+                red_packet.add_pending_claim(claimer_id.clone(), token_id, amount);
+            };
+        };
+        let ft_storage_deposit = match red_packet.token {
+            Token::FungibleToken if claim_amount.0 != 0 => red_packet.virtual_take_ft_storage_deposit(),
+            _ => None
+        };
+        let released_balance = if claim_amount.0 != 0 && red_packet.is_run_out() {
+            Some(red_packet.virtual_release_reserves())
+        } else {
+            None
+        };
 
         self.measure_start();
-        self.red_packets.insert(&public_key, &red_packet);
+        self.save_red_packet(&public_key, &red_packet);
         self.measure_end(&red_packet.owner_id);
 
+        if let Some(released_balance) = released_balance {
+            self.delete_red_packet_access_key(
+                public_key.clone(),
+                red_packet.owner_id.clone(),
+                red_packet.refund_to_balance,
+                released_balance
+            );
+        };
+
+        if claim_amount.0 != 0 {
+            self.internal_record_claim(&claimer_id, public_key.clone(), claim_amount);
+        };
+
+        if claim_amount.0 != 0 {
+            let mut claim_event_data = vec![EventData {
+                account_id: Some(&claimer_id),
+                amount: Some(claim_amount),
+                nft_token_id: red_packet.claimed_nft_token_ids.get(&claimer_id),
+                ..EventData::from_red_packet(&public_key, &red_packet)
+            }];
+            for leg in &red_packet.bundle {
+                claim_event_data.push(EventData {
+                    token: Some(&Token::FungibleToken),
+                    token_id: Some(&leg.token_id),
+                    account_id: Some(&claimer_id),
+                    amount: leg.claimers.get(&claimer_id).copied(),
+                    ..EventData::from_red_packet(&public_key, &red_packet)
+                });
+            };
+            emit_event(EventKind::Claim, claim_event_data);
+        };
+        if leftover.0 != 0 {
+            emit_event(EventKind::Refund, vec![EventData {
+                amount: Some(leftover),
+                ..EventData::from_red_packet(&public_key, &red_packet)
+            }]);
+        };
+
         if claim_amount.0 != 0 {
             match red_packet.token {
                 Token::NEAR => {
@@ -200,68 +612,455 @@ impl Contract {
                         // create new account
                         // not implemented in this method
                     } else {
-                        transfer(claimer_id, claim_amount.0);
+                        payout(claimer_id.clone(), Token::NEAR, None, claim_amount);
                     }
                 },
                 Token::FungibleToken => {
                     if create {
                         panic!("Fungible Token can not be used to create account")
                     } else {
-                        transfer_ft_with_resolve_claim_fungible_token_red_packet(
-                            claimer_id,
+                        match ft_storage_deposit {
+                            Some(storage_deposit) => {
+                                transfer_ft_with_storage_check_and_resolve_claim_fungible_token_red_packet(
+                                    claimer_id.clone(),
+                                    claim_amount,
+                                    red_packet.token_id.clone().unwrap(),
+                                    red_packet.owner_id.clone(),
+                                    public_key.clone(),
+                                    storage_deposit
+                                );
+                            },
+                            None => {
+                                transfer_ft_with_resolve_claim_fungible_token_red_packet(
+                                    claimer_id.clone(),
+                                    claim_amount,
+                                    red_packet.token_id.clone().unwrap(),
+                                    red_packet.owner_id.clone(),
+                                    public_key.clone()
+                                );
+                            }
+                        };
+                    }
+                },
+                Token::NonFungibleToken => {
+                    if create {
+                        panic!("Non Fungible Token can not be used to create account")
+                    } else {
+                        transfer_nft_with_resolve_claim_non_fungible_token_red_packet(
+                            claimer_id.clone(),
+                            red_packet.claimed_nft_token_ids.get(&claimer_id).unwrap().clone(),
+                            red_packet.token_id.clone().unwrap(),
+                            red_packet.owner_id.clone(),
+                            public_key.clone()
+                        );
+                    }
+                },
+                Token::MultiToken { ref token_id } => {
+                    if create {
+                        panic!("Multi Token can not be used to create account")
+                    } else {
+                        transfer_mt_with_resolve_claim_multi_token_red_packet(
+                            claimer_id.clone(),
                             claim_amount,
+                            token_id.clone(),
                             red_packet.token_id.clone().unwrap(),
-                            red_packet.owner_id,
-                            public_key
+                            red_packet.owner_id.clone(),
+                            public_key.clone()
                         );
                     }
                 }
             };
+
+            if !red_packet.bundle.is_empty() && create {
+                panic!("Bundle red packet can not be used to create account")
+            };
+            for leg in &red_packet.bundle {
+                let leg_claim_amount = leg.claimers.get(&claimer_id).copied().unwrap_or(U128(0));
+                if leg_claim_amount.0 != 0 {
+                    transfer_ft_with_resolve_claim_fungible_token_red_packet(
+                        claimer_id.clone(),
+                        leg_claim_amount,
+                        leg.token_id.clone(),
+                        red_packet.owner_id.clone(),
+                        public_key.clone()
+                    );
+                };
+            };
+        };
+
+        // every claimer gets one non fungible token, no leftover
+        if leftover.0 != 0 && !matches!(red_packet.token, Token::NonFungibleToken) {
+            self.refund_to_owner(red_packet.owner_id, red_packet.refund_to_balance, red_packet.token, red_packet.token_id, leftover);
         };
 
         claim_amount
     }
 
     pub fn internal_refund(&mut self, public_key: PublicKey) -> U128 {
-        let owner_id = env::predecessor_account_id();
+        let (refund_amount, _) = self.refund_red_packet(public_key, env::predecessor_account_id(), true);
+        refund_amount
+    }
 
+    /// Return refunded balance and refunded bundle legs,
+    /// forced refund ignores `refund_to_balance` of the red packet
+    pub fn refund_red_packet(
+        &mut self,
+        public_key: PublicKey,
+        operator_id: AccountId,
+        allow_refund_to_balance: bool
+    ) -> (U128, Vec<(AccountId, U128)>) {
         let mut red_packet = self.red_packets
             .get(&public_key)
             .expect(ERR_01_NO_MATCHING_RED_PACKET);
-        let refund_amount = red_packet.virtual_refund(owner_id.clone()).unwrap();
+        let nft_token_ids = red_packet.nft_token_ids.clone();
+        let bundle_refunds: Vec<(AccountId, U128)> = red_packet.bundle
+            .iter()
+            .map(|leg| (leg.token_id.clone(), leg.current_balance))
+            .collect();
+        let refund_amount = red_packet.virtual_refund(operator_id.clone()).unwrap();
+        let released_balance = if refund_amount.0 != 0 {
+            red_packet.virtual_release_reserves()
+        } else {
+            U128(0)
+        };
+        let owner_id = red_packet.owner_id.clone();
+        let refund_to_balance = red_packet.refund_to_balance && allow_refund_to_balance;
+
+        if refund_amount.0 != 0 {
+            let mut refund_event_data = vec![EventData {
+                account_id: Some(&operator_id),
+                amount: Some(refund_amount),
+                ..EventData::from_red_packet(&public_key, &red_packet)
+            }];
+            for (token_id, amount) in &bundle_refunds {
+                refund_event_data.push(EventData {
+                    token: Some(&Token::FungibleToken),
+                    token_id: Some(token_id),
+                    account_id: Some(&operator_id),
+                    amount: Some(*amount),
+                    ..EventData::from_red_packet(&public_key, &red_packet)
+                });
+            };
+            emit_event(EventKind::Refund, refund_event_data);
+        };
 
         self.measure_start();
-        self.red_packets.insert(&public_key, &red_packet);
+        self.save_red_packet(&public_key, &red_packet);
         self.measure_end(&owner_id);
 
         if refund_amount.0 != 0 {
+            self.delete_red_packet_access_key(public_key, owner_id.clone(), refund_to_balance, released_balance);
             match red_packet.token {
-                Token::NEAR => {
-                    transfer(owner_id, refund_amount.0);
+                Token::NonFungibleToken => {
+                    payout_nft(owner_id.clone(), red_packet.token_id.unwrap(), nft_token_ids);
                 },
-                Token::FungibleToken => {
-                    transfer_ft(owner_id, refund_amount, red_packet.token_id.unwrap());
+                token => {
+                    self.refund_to_owner(owner_id.clone(), refund_to_balance, token, red_packet.token_id, refund_amount);
                 }
-            }
+            };
+            for (token_id, amount) in &bundle_refunds {
+                if amount.0 != 0 {
+                    self.refund_to_owner(owner_id.clone(), refund_to_balance, Token::FungibleToken, Some(token_id.clone()), *amount);
+                };
+            };
         }
 
+        (refund_amount, bundle_refunds)
+    }
+
+    /// Refund every live red packet of the owner before forced unregister,
+    /// return refunded amounts per token.
+    /// Panic if the payouts do not fit in one call, the owner has to `refund` some red packets first
+    pub fn refund_red_packets(&mut self, owner_id: &AccountId) -> Vec<(Token, Option<AccountId>, U128)> {
+        let public_keys = self.owners.get(owner_id).unwrap_or_default();
+        let refund_gas: u64 = public_keys
+            .iter()
+            .map(|public_key| forced_refund_gas(&self.red_packets.get(public_key).unwrap()).0)
+            .sum();
+        require!(refund_gas <= MAX_GAS_FOR_FORCED_REFUNDS.0, ERR_29_TOO_MANY_RED_PACKETS_TO_REFUND);
+
+        let mut refunded: Vec<(Token, Option<AccountId>, U128)> = vec![];
+        let mut add_refunded = |token: Token, token_id: Option<AccountId>, amount: U128| {
+            if amount.0 == 0 {
+                return;
+            };
+            match refunded
+                .iter_mut()
+                .find(|(refunded_token, refunded_token_id, _)| *refunded_token == token && *refunded_token_id == token_id) {
+                Some((_, _, total)) => total.0 += amount.0,
+                None => refunded.push((token, token_id, amount))
+            };
+        };
+
+        for public_key in public_keys {
+            let red_packet = self.red_packets.get(&public_key).unwrap();
+            if red_packet.is_run_out() {
+                continue;
+            };
+            let (refund_amount, bundle_refunds) = self.refund_red_packet(public_key, owner_id.clone(), false);
+            add_refunded(red_packet.token, red_packet.token_id, refund_amount);
+            for (token_id, amount) in bundle_refunds {
+                add_refunded(Token::FungibleToken, Some(token_id), amount);
+            };
+        };
+        refunded
+    }
+
+    pub fn internal_retry_claim(&mut self, public_key: PublicKey) -> U128 {
+        let claimer_id = env::predecessor_account_id();
+        let mut red_packet = self.red_packets
+            .get(&public_key)
+            .expect(ERR_01_NO_MATCHING_RED_PACKET);
+        let amount = red_packet.virtual_retry_claim(&claimer_id).unwrap();
+        red_packet.add_pending_claim(claimer_id.clone(), red_packet.token_id.clone().unwrap(), amount);
+
+        self.measure_start();
+        self.save_red_packet(&public_key, &red_packet);
+        self.measure_end(&red_packet.owner_id);
+
+        transfer_ft_with_resolve_retry_claim(
+            claimer_id,
+            amount,
+            red_packet.token_id.unwrap(),
+            red_packet.owner_id,
+            public_key
+        );
+
+        amount
+    }
+
+    pub fn internal_refund_expired_retry_claims(&mut self, public_key: PublicKey) -> U128 {
+        let mut red_packet = self.red_packets
+            .get(&public_key)
+            .expect(ERR_01_NO_MATCHING_RED_PACKET);
+        let (refund_claimers, refund_amount) = red_packet.virtual_refund_retry_claims(false);
+        let owner_id = red_packet.owner_id.clone();
+        for claimer_id in &refund_claimers {
+            self.internal_mark_claim_failed(claimer_id, &public_key);
+        };
+
+        if refund_amount.0 != 0 {
+            emit_event(EventKind::Refund, vec![EventData {
+                account_id: Some(&env::predecessor_account_id()),
+                amount: Some(refund_amount),
+                ..EventData::from_red_packet(&public_key, &red_packet)
+            }]);
+        };
+
+        self.measure_start();
+        self.save_red_packet(&public_key, &red_packet);
+        self.measure_end(&owner_id);
+
+        if refund_amount.0 != 0 {
+            self.refund_to_owner(owner_id, red_packet.refund_to_balance, red_packet.token, red_packet.token_id, refund_amount);
+        };
+
         refund_amount
     }
 
+    pub fn internal_create_red_packet_from_balance(
+        &mut self,
+        balance: WithdrawableBalance,
+        public_key: PublicKey,
+        split: usize,
+        split_mod: SplitMod,
+        options: RedPacketOptions
+    ) {
+        require!(balance.nft_token_id.is_none(), ERR_04_INVALID_PARAMETER);
+        let funder_id = env::predecessor_account_id();
+
+        self.measure_start();
+        let amount = self.internal_debit(&funder_id, &balance.token, &balance.token_id, Some(balance.amount));
+        self.measure_end(&funder_id);
+
+        match balance.token {
+            Token::NEAR => {
+                self.internal_create_near_red_packet(
+                    funder_id,
+                    amount.0,
+                    public_key,
+                    split,
+                    split_mod,
+                    options
+                );
+            },
+            Token::FungibleToken | Token::MultiToken { .. } => {
+                self.internal_create_fungible_token_red_packet(
+                    WithdrawableBalance { amount, ..balance },
+                    funder_id,
+                    public_key,
+                    split,
+                    split_mod,
+                    options
+                );
+            },
+            Token::NonFungibleToken => panic!("{}", ERR_04_INVALID_PARAMETER)
+        };
+    }
+
+    pub fn internal_withdraw(&mut self, token: Token, token_id: Option<AccountId>, amount: Option<U128>) -> U128 {
+        assert_one_yocto();
+        require!(!matches!(token, Token::NonFungibleToken), ERR_04_INVALID_PARAMETER);
+        let account_id = env::predecessor_account_id();
+        let registered = self.storage_manager.account_registered(&account_id);
+        if registered {
+            self.measure_start();
+        };
+        let amount = self.internal_debit(&account_id, &token, &token_id, amount);
+        if registered {
+            self.measure_end(&account_id);
+        };
+
+        emit_event(EventKind::Withdraw, vec![EventData {
+            token: Some(&token),
+            token_id: token_id.as_ref(),
+            account_id: Some(&account_id),
+            amount: Some(amount),
+            ..Default::default()
+        }]);
+
+        payout(account_id, token, token_id, amount);
+
+        amount
+    }
+
+    pub fn internal_withdraw_nft(&mut self, token_id: AccountId, nft_token_id: TokenId) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let registered = self.storage_manager.account_registered(&account_id);
+        if registered {
+            self.measure_start();
+        };
+        self.internal_debit_nft(&account_id, &token_id, &nft_token_id);
+        if registered {
+            self.measure_end(&account_id);
+        };
+
+        emit_event(EventKind::Withdraw, vec![EventData {
+            token: Some(&Token::NonFungibleToken),
+            token_id: Some(&token_id),
+            account_id: Some(&account_id),
+            amount: Some(U128(1)),
+            nft_token_id: Some(&nft_token_id),
+            ..Default::default()
+        }]);
+
+        payout_nft(account_id, token_id, vec![nft_token_id]);
+    }
+
+    pub fn internal_transfer_red_packet_ownership(&mut self, public_key: PublicKey, new_owner_id: AccountId) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+
+        let mut red_packet = self.red_packets
+            .get(&public_key)
+            .expect(ERR_01_NO_MATCHING_RED_PACKET);
+        require!(red_packet.owner_id == owner_id, ERR_02_NO_PERMISSION_TO_RED_PACKET);
+        require!(new_owner_id != owner_id, ERR_04_INVALID_PARAMETER);
+        self.storage_manager.assert_registration(&new_owner_id);
+        // storage is charged to the new owner, who has to approve the current owner as a funder
+        self.assert_approved_funder(&new_owner_id, &owner_id);
+
+        self.measure_start();
+        self.remove_red_packet(&public_key, &owner_id, true);
+        self.measure_end(&owner_id);
+
+        red_packet.owner_id = new_owner_id.clone();
+
+        emit_event(EventKind::TransferOwnership, vec![EventData {
+            account_id: Some(&owner_id),
+            ..EventData::from_red_packet(&public_key, &red_packet)
+        }]);
+
+        self.measure_start();
+        self.add_red_packet(new_owner_id.clone(), public_key, red_packet);
+        self.measure_end(&new_owner_id);
+
+        self.assert_after_creation(&new_owner_id);
+    }
+
+    pub fn internal_approve_funder(&mut self, funder_id: AccountId) {
+        let owner_id = env::predecessor_account_id();
+        self.storage_manager.assert_registration(&owner_id);
+
+        let mut funders = self.approved_funders.get(&owner_id).unwrap_or_default();
+        funders.insert(funder_id);
+        self.measure_start();
+        self.approved_funders.insert(&owner_id, &funders);
+        self.measure_end(&owner_id);
+
+        self.assert_after_creation(&owner_id);
+    }
+
+    pub fn internal_revoke_funder(&mut self, funder_id: AccountId) {
+        let owner_id = env::predecessor_account_id();
+        let mut funders = self.approved_funders.get(&owner_id).unwrap_or_default();
+        if !funders.remove(&funder_id) {
+            return;
+        };
+        self.measure_start();
+        if funders.is_empty() {
+            self.approved_funders.remove(&owner_id);
+        } else {
+            self.approved_funders.insert(&owner_id, &funders);
+        };
+        self.measure_end(&owner_id);
+    }
+
     pub fn internal_remove_history(&mut self, public_key: PublicKey) {
         let owner_id = env::predecessor_account_id();
 
         self.measure_start();
-        self.remove_red_packet(&public_key,&owner_id,false);
+        let removed = self.remove_red_packet(&public_key,&owner_id,false);
         self.measure_end(&owner_id);
+
+        if removed {
+            emit_event(EventKind::RemoveHistory, vec![EventData {
+                public_key: Some(&public_key),
+                owner_id: Some(&owner_id),
+                ..Default::default()
+            }]);
+        };
+    }
+
+    pub fn internal_clear_stale_pending_claims(&mut self, public_key: PublicKey) -> usize {
+        let owner_id = env::predecessor_account_id();
+
+        let mut red_packet = self.red_packets
+            .get(&public_key)
+            .expect(ERR_01_NO_MATCHING_RED_PACKET);
+        require!(red_packet.owner_id == owner_id, ERR_02_NO_PERMISSION_TO_RED_PACKET);
+
+        let cleared = red_packet.virtual_clear_stale_pending_claims();
+        if cleared != 0 {
+            log!("Clear stale pending claims, public key: {:?}, count: {}", public_key, cleared);
+            self.measure_start();
+            self.save_red_packet(&public_key, &red_packet);
+            self.measure_end(&owner_id);
+        };
+        cleared
     }
 
     pub fn internal_clear_history(&mut self) {
         let owner_id = env::predecessor_account_id();
 
         self.measure_start();
-        self.clear_red_packets(&owner_id,false);
+        let removed_public_keys = self.clear_red_packets(&owner_id,false);
         self.measure_end(&owner_id);
+
+        if !removed_public_keys.is_empty() {
+            emit_event(
+                EventKind::ClearHistory,
+                removed_public_keys
+                    .iter()
+                    .map(|public_key| EventData {
+                        public_key: Some(public_key),
+                        owner_id: Some(&owner_id),
+                        ..Default::default()
+                    })
+                    .collect()
+            );
+        };
     }
 }
 
@@ -276,19 +1075,23 @@ impl Contract {
         let mut public_keys = self.owners.get(&owner_id).unwrap_or(HashSet::new());
         public_keys.insert(public_key.clone());
         self.owners.insert(&owner_id, &public_keys);
-        self.red_packets.insert(&public_key, &red_packet);
+        self.save_red_packet(&public_key, &red_packet);
     }
 
-    pub fn remove_red_packet(&mut self, public_key: &PublicKey, owner_id: &AccountId, force: bool) {
+    /// Return whether red packet is removed
+    pub fn remove_red_packet(&mut self, public_key: &PublicKey, owner_id: &AccountId, force: bool) -> bool {
         match self.red_packets.get(public_key) {
             None => {
-                return;
+                return false;
             }
             Some(red_packet) => {
                 if *owner_id != red_packet.owner_id {
                     panic!("{}", ERR_02_NO_PERMISSION_TO_RED_PACKET);
                 };
-                if !red_packet.is_run_out() && !force {
+                if red_packet.has_pending_claims() && !force {
+                    panic!("{}", ERR_23_RED_PACKET_HAS_PENDING_CLAIMS);
+                };
+                if !red_packet.is_finished() && !force {
                     panic!("{}", ERR_03_RED_PACKET_NOT_RUN_OUT);
                 };
 
@@ -300,24 +1103,46 @@ impl Contract {
                 } else {
                     self.owners.insert(owner_id, &public_keys);
                 }
-                self.red_packets.remove(public_key);
+                self.drop_red_packet(public_key);
+                true
             }
-        };
+        }
     }
 
-    pub fn clear_red_packets(&mut self, owner_id: &AccountId, force: bool) {
+    /// Return public keys of removed red packets
+    pub fn clear_red_packets(&mut self, owner_id: &AccountId, force: bool) -> Vec<PublicKey> {
         match self.owners.get(owner_id) {
             None => {
-                return;
+                vec![]
             }
             Some(mut public_keys) => {
+                let mut removed_public_keys = vec![];
                 public_keys.retain(|public_key| {
-                    let red_packet = self.red_packets.get(public_key).unwrap();
-                    let is_run_out = red_packet.is_run_out();
-                    if is_run_out || force {
-                        self.red_packets.remove(public_key);
+                    let mut red_packet = self.red_packets.get(public_key).unwrap();
+                    let is_finished = red_packet.is_finished();
+                    if !red_packet.is_run_out() && force {
+                        // access key of run out red packet is already deleted
+                        self.delete_red_packet_access_key(
+                            public_key.clone(),
+                            owner_id.clone(),
+                            false,
+                            red_packet.virtual_release_reserves()
+                        );
+                    };
+                    if !is_finished && force {
+                        let (escrow_claimers, escrow_amount) = red_packet.virtual_refund_retry_claims(true);
+                        for claimer_id in &escrow_claimers {
+                            self.internal_mark_claim_failed(claimer_id, public_key);
+                        };
+                        if escrow_amount.0 != 0 {
+                            payout(owner_id.clone(), Token::FungibleToken, red_packet.token_id.clone(), escrow_amount);
+                        };
                     };
-                    !is_run_out && !force
+                    if is_finished || force {
+                        self.drop_red_packet(public_key);
+                        removed_public_keys.push(public_key.clone());
+                    };
+                    !is_finished && !force
                 });
 
                 if public_keys.is_empty() {
@@ -325,10 +1150,49 @@ impl Contract {
                 } else {
                     self.owners.insert(owner_id, &public_keys);
                 }
+                removed_public_keys
             }
+        }
+    }
+
+    /// Refunds of red packets created with `refund_to_balance` stay in the internal balance
+    pub fn refund_to_owner(
+        &mut self,
+        owner_id: AccountId,
+        refund_to_balance: bool,
+        token: Token,
+        token_id: Option<AccountId>,
+        amount: U128
+    ) {
+        if refund_to_balance {
+            self.measure_start();
+            self.internal_credit(&owner_id, token, token_id, amount);
+            self.measure_end(&owner_id);
+        } else {
+            payout(owner_id, token, token_id, amount);
+        }
+    }
+
+    /// Delete access key of an ended red packet, released reserves go back to the owner
+    pub fn delete_red_packet_access_key(
+        &mut self,
+        public_key: PublicKey,
+        owner_id: AccountId,
+        refund_to_balance: bool,
+        released_balance: U128
+    ) {
+        delete_access_key(public_key);
+        if released_balance.0 != 0 {
+            self.refund_to_owner(owner_id, refund_to_balance, Token::NEAR, None, released_balance);
         };
     }
 
+    /// Token red packets pay the access key allowance and storage reserve from storage balance
+    pub fn pay_from_storage_balance(&mut self, owner_id: &AccountId, amount: Balance) {
+        let paid = self.storage_manager.withdraw_storage_balance(owner_id, Some(U128(amount)));
+        require!(paid == amount, ERR_18_NOT_ENOUGH_BALANCE_FOR_ALLOWANCE);
+    }
+
     pub fn red_packet_count(&self, owner_id: &AccountId) -> (usize, usize) {
         let mut total = 0;
         let mut run_out = 0;
@@ -339,7 +1203,7 @@ impl Contract {
                 total = public_keys.len();
                 for public_key in public_keys {
                     let red_packet = self.red_packets.get(&public_key).unwrap();
-                    if red_packet.is_run_out() {
+                    if red_packet.is_finished() {
                         run_out += 1;
                     };
                 };
@@ -347,6 +1211,42 @@ impl Contract {
         };
         (total, run_out)
     }
+    /// Filter on summaries without reading whole red packets, public keys are sorted to keep pages stable
+    pub fn filter_red_packets_by_owner_id(
+        &self,
+        owner_id: &AccountId,
+        filter: Option<RedPacketFilter>,
+        from_index: Option<u64>,
+        limit: Option<u64>
+    ) -> Vec<(PublicKey, RedPacketSummary)> {
+        let mut public_keys: Vec<PublicKey> = self.owners
+            .get(owner_id)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        public_keys.sort();
+        public_keys
+            .into_iter()
+            .map(|public_key| {
+                let summary = self.summaries.get(&public_key).unwrap();
+                (public_key, summary)
+            })
+            .filter(|(_, summary)| match &filter {
+                Some(filter) => filter.matches(summary),
+                None => true
+            })
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_VIEW_LIMIT) as usize)
+            .collect()
+    }
+
+    pub fn has_pending_claims(&self, owner_id: &AccountId) -> bool {
+        self.owners
+            .get(owner_id)
+            .unwrap_or_default()
+            .iter()
+            .any(|public_key| self.red_packets.get(public_key).unwrap().has_pending_claims())
+    }
 
     pub fn all_red_packets_run_out(&self, owner_id: &AccountId) -> bool {
         let count = self.red_packet_count(owner_id);
@@ -376,6 +1276,14 @@ impl Contract {
         self.assert_unique_public_key(public_key);
     }
 
+    /// Storage, allowance and reserves are charged to the owner, so a different funder needs the owner's approval
+    pub fn assert_approved_funder(&self, owner_id: &AccountId, funder_id: &AccountId) {
+        require!(
+            owner_id == funder_id || self.approved_funders.get(owner_id).unwrap_or_default().contains(funder_id),
+            ERR_25_FUNDER_NOT_APPROVED
+        );
+    }
+
     pub fn assert_after_creation(&self, account_id: &AccountId) {
         self.storage_manager.assert_storage_balance(account_id);
     }
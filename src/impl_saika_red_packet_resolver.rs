@@ -1,11 +1,15 @@
 use crate::Contract;
 use crate::ContractExt;
 use crate::errors::*;
-use crate::utils::transfer_ft;
+use crate::utils::*;
 use crate::saika_red_packet_resolver::SaikaRedPacketResolver;
+use crate::enums::Token;
+use crate::events::{emit_event, EventData, EventKind};
 
-use near_sdk::{AccountId, is_promise_success, log, near_bindgen, PublicKey};
+use near_sdk::{AccountId, Balance, env, is_promise_success, log, near_bindgen, PromiseResult, PublicKey, serde_json};
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
 use near_sdk::json_types::U128;
+use near_contract_standards::non_fungible_token::TokenId;
 
 
 #[near_bindgen]
@@ -19,16 +23,324 @@ impl SaikaRedPacketResolver for Contract {
         token_id: AccountId,
         public_key: PublicKey
     ) {
-        if !is_promise_success() {
+        let success = is_promise_success();
+        let mut escrowed = false;
+        // ownership may be transferred while the transfer is in flight
+        let mut owner_id = owner_id;
+        let mut refund_to_balance = false;
+        if let Some(mut red_packet) = self.red_packets.get(&public_key) {
+            owner_id = red_packet.owner_id.clone();
+            refund_to_balance = red_packet.refund_to_balance;
+            red_packet.remove_pending_claim(&claimer_id, &token_id);
+            if !success {
+                if red_packet.token_id.as_ref() == Some(&token_id) {
+                    if red_packet.retryable_claims {
+                        red_packet.virtual_escrow_failed_claim(claimer_id.clone(), amount);
+                        escrowed = true;
+                    } else {
+                        red_packet.failed_claimer(claimer_id.clone(), amount);
+                        self.internal_mark_claim_failed(&claimer_id, &public_key);
+                    };
+                } else if let Some(leg) = red_packet.bundle_leg_mut(&token_id) {
+                    leg.failed_claimer(claimer_id.clone(), amount);
+                };
+            };
+            self.measure_start();
+            self.save_red_packet(&public_key, &red_packet);
+            self.measure_end(&red_packet.owner_id);
+        };
+
+        if !success {
             log!("{}", ERR_09_CLAIM_FT_RED_PACKET_FAILED);
-            if let Some(mut red_packet) = self.red_packets.get(&public_key) {
-                red_packet.failed_claimer(claimer_id, amount);
-                self.red_packets.insert(&public_key, &red_packet);
+            emit_event(EventKind::ClaimFailed, vec![EventData {
+                public_key: Some(&public_key),
+                token: Some(&Token::FungibleToken),
+                token_id: Some(&token_id),
+                owner_id: Some(&owner_id),
+                account_id: Some(&claimer_id),
+                amount: Some(amount),
+                ..Default::default()
+            }]);
+            if escrowed {
+                log!("Escrow balance for claimer, claimer id: {}, amount: {}, token id: {}", claimer_id, amount.0, token_id);
+            } else {
+                log!("Refund balance to red packet owner, owner id: {}, amount: {}, token id: {}", owner_id, amount.0, token_id);
+                self.refund_to_owner(owner_id, refund_to_balance, Token::FungibleToken, Some(token_id), amount);
             };
-            log!("Refund balance to red packet owner, owner id: {}, amount: {}, token id: {}", owner_id, amount.0, token_id);
-            transfer_ft(owner_id, amount, token_id);
         } else {
             log!("Success claim fungible token red packet, amount: {}, token id: {}", amount.0, token_id);
         }
     }
-}
\ No newline at end of file
+
+    /// Failed payout is credited to the internal ledger of the receiver
+    #[private]
+    fn resolve_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token: Token,
+        token_id: Option<AccountId>,
+        amount: U128
+    ) {
+        if !is_promise_success() {
+            log!("Failed payout is credited, receiver id: {}, amount: {}", receiver_id, amount.0);
+            emit_event(EventKind::Credit, vec![EventData {
+                token: Some(&token),
+                token_id: token_id.as_ref(),
+                account_id: Some(&receiver_id),
+                amount: Some(amount),
+                ..Default::default()
+            }]);
+            self.internal_credit(&receiver_id, token, token_id, amount);
+        }
+    }
+
+    /// Credit every failed non fungible token transfer of `payout_nft`
+    #[private]
+    fn resolve_payout_nft(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: AccountId,
+        nft_token_ids: Vec<TokenId>
+    ) {
+        for (index, nft_token_id) in nft_token_ids.into_iter().enumerate() {
+            if let PromiseResult::Successful(_) = env::promise_result(index as u64) {
+                continue;
+            };
+            log!("Failed payout is credited, receiver id: {}, nft token id: {}, token id: {}", receiver_id, nft_token_id, token_id);
+            emit_event(EventKind::Credit, vec![EventData {
+                token: Some(&Token::NonFungibleToken),
+                token_id: Some(&token_id),
+                account_id: Some(&receiver_id),
+                amount: Some(U128(1)),
+                nft_token_id: Some(&nft_token_id),
+                ..Default::default()
+            }]);
+            self.internal_credit_nft(&receiver_id, token_id.clone(), nft_token_id);
+        };
+    }
+
+    #[private]
+    fn resolve_retry_claim(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        amount: U128,
+        token_id: AccountId,
+        public_key: PublicKey
+    ) {
+        let success = is_promise_success();
+        if success {
+            log!("Success retry claim fungible token red packet, amount: {}, token id: {}", amount.0, token_id);
+        } else {
+            log!("{}", ERR_09_CLAIM_FT_RED_PACKET_FAILED);
+        };
+        match self.red_packets.get(&public_key) {
+            Some(mut red_packet) => {
+                red_packet.remove_pending_claim(&claimer_id, &token_id);
+                red_packet.finish_retry_claim(&claimer_id, success);
+                if success {
+                    emit_event(EventKind::Claim, vec![EventData {
+                        account_id: Some(&claimer_id),
+                        amount: Some(amount),
+                        ..EventData::from_red_packet(&public_key, &red_packet)
+                    }]);
+                };
+                self.measure_start();
+                self.save_red_packet(&public_key, &red_packet);
+                self.measure_end(&red_packet.owner_id);
+            },
+            None => {
+                // escrow of removed red packet belongs to the owner
+                if !success {
+                    log!("Refund balance to red packet owner, owner id: {}, amount: {}, token id: {}", owner_id, amount.0, token_id);
+                    payout(owner_id, Token::FungibleToken, Some(token_id), amount);
+                };
+            }
+        };
+    }
+
+    /// Register the claimer on the token contract with the minimum storage deposit of the token contract if needed,
+    /// the unused part of the reserved deposit goes back to the red packet reserve,
+    /// a minimum above the reserved deposit is taken from the reserve, if the reserve is short the claimer is not registered
+    #[private]
+    fn resolve_ft_storage_balance_of_claimer(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        amount: U128,
+        token_id: AccountId,
+        public_key: PublicKey,
+        storage_deposit: U128
+    ) {
+        let registered = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<Option<StorageBalance>>(&value)
+                .ok()
+                .flatten()
+                .is_some(),
+            _ => false
+        };
+
+        // fall back to the reserved deposit if the token contract does not report its bounds
+        let min_storage_deposit = match env::promise_result(1) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<StorageBalanceBounds>(&value)
+                .map(|bounds| bounds.min.0)
+                .unwrap_or(storage_deposit.0),
+            _ => storage_deposit.0
+        };
+
+        let storage_deposit = if registered {
+            self.internal_return_ft_storage_deposit(&public_key, &owner_id, storage_deposit.0);
+            None
+        } else if min_storage_deposit <= storage_deposit.0 {
+            self.internal_return_ft_storage_deposit(&public_key, &owner_id, storage_deposit.0 - min_storage_deposit);
+            Some(U128(min_storage_deposit))
+        } else if self.internal_take_ft_storage_deposit(&public_key, min_storage_deposit - storage_deposit.0) {
+            Some(U128(min_storage_deposit))
+        } else {
+            log!("Not enough storage reserve to register claimer, claimer id: {}, token id: {}", claimer_id, token_id);
+            self.internal_return_ft_storage_deposit(&public_key, &owner_id, storage_deposit.0);
+            None
+        };
+
+        if let Some(storage_deposit) = storage_deposit {
+            log!("Register claimer on token contract, claimer id: {}, token id: {}", claimer_id, token_id);
+            storage_deposit_and_transfer_ft_with_resolve_claim_fungible_token_red_packet(
+                claimer_id,
+                amount,
+                token_id,
+                owner_id,
+                public_key,
+                storage_deposit
+            );
+        } else {
+            transfer_ft_with_resolve_claim_fungible_token_red_packet(
+                claimer_id,
+                amount,
+                token_id,
+                owner_id,
+                public_key
+            );
+        }
+    }
+
+    #[private]
+    fn resolve_claim_non_fungible_token_red_packet(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        nft_token_id: TokenId,
+        token_id: AccountId,
+        public_key: PublicKey
+    ) {
+        let success = is_promise_success();
+        // ownership may be transferred while the transfer is in flight
+        let mut owner_id = owner_id;
+        if let Some(mut red_packet) = self.red_packets.get(&public_key) {
+            owner_id = red_packet.owner_id.clone();
+            red_packet.remove_pending_claim(&claimer_id, &token_id);
+            if !success {
+                red_packet.failed_claimer(claimer_id.clone(), U128(1));
+                self.internal_mark_claim_failed(&claimer_id, &public_key);
+            };
+            self.measure_start();
+            self.save_red_packet(&public_key, &red_packet);
+            self.measure_end(&red_packet.owner_id);
+        };
+
+        if !success {
+            log!("{}", ERR_14_CLAIM_NFT_RED_PACKET_FAILED);
+            emit_event(EventKind::ClaimFailed, vec![EventData {
+                public_key: Some(&public_key),
+                token: Some(&Token::NonFungibleToken),
+                token_id: Some(&token_id),
+                owner_id: Some(&owner_id),
+                account_id: Some(&claimer_id),
+                amount: Some(U128(1)),
+                nft_token_id: Some(&nft_token_id),
+                ..Default::default()
+            }]);
+            log!("Refund non fungible token to red packet owner, owner id: {}, nft token id: {}, token id: {}", owner_id, nft_token_id, token_id);
+            payout_nft(owner_id, token_id, vec![nft_token_id]);
+        } else {
+            log!("Success claim non fungible token red packet, nft token id: {}, token id: {}", nft_token_id, token_id);
+        }
+    }
+
+    #[private]
+    fn resolve_claim_multi_token_red_packet(
+        &mut self,
+        claimer_id: AccountId,
+        owner_id: AccountId,
+        amount: U128,
+        token_id: AccountId,
+        mt_token_id: String,
+        public_key: PublicKey
+    ) {
+        let success = is_promise_success();
+        // ownership may be transferred while the transfer is in flight
+        let mut owner_id = owner_id;
+        let mut refund_to_balance = false;
+        if let Some(mut red_packet) = self.red_packets.get(&public_key) {
+            owner_id = red_packet.owner_id.clone();
+            refund_to_balance = red_packet.refund_to_balance;
+            red_packet.remove_pending_claim(&claimer_id, &token_id);
+            if !success {
+                red_packet.failed_claimer(claimer_id.clone(), amount);
+                self.internal_mark_claim_failed(&claimer_id, &public_key);
+            };
+            self.measure_start();
+            self.save_red_packet(&public_key, &red_packet);
+            self.measure_end(&red_packet.owner_id);
+        };
+
+        if !success {
+            log!("{}", ERR_15_CLAIM_MT_RED_PACKET_FAILED);
+            emit_event(EventKind::ClaimFailed, vec![EventData {
+                public_key: Some(&public_key),
+                token: Some(&Token::MultiToken { token_id: mt_token_id.clone() }),
+                token_id: Some(&token_id),
+                owner_id: Some(&owner_id),
+                account_id: Some(&claimer_id),
+                amount: Some(amount),
+                ..Default::default()
+            }]);
+            log!("Refund multi token to red packet owner, owner id: {}, amount: {}, mt token id: {}, token id: {}", owner_id, amount.0, mt_token_id, token_id);
+            self.refund_to_owner(owner_id, refund_to_balance, Token::MultiToken { token_id: mt_token_id }, Some(token_id), amount);
+        } else {
+            log!("Success claim multi token red packet, amount: {}, mt token id: {}, token id: {}", amount.0, mt_token_id, token_id);
+        }
+    }
+}
+
+impl Contract {
+    /// Put an unused storage deposit back to the red packet reserve, or pay it to the owner if the red packet ended
+    fn internal_return_ft_storage_deposit(&mut self, public_key: &PublicKey, owner_id: &AccountId, amount: Balance) {
+        if amount == 0 {
+            return;
+        };
+        match self.red_packets.get(public_key) {
+            Some(mut red_packet) if !red_packet.is_run_out() => {
+                red_packet.ft_storage_reserve = U128(red_packet.ft_storage_reserve.0 + amount);
+                self.save_red_packet(public_key, &red_packet);
+            },
+            Some(red_packet) => {
+                payout(red_packet.owner_id, Token::NEAR, None, U128(amount));
+            },
+            None => {
+                payout(owner_id.clone(), Token::NEAR, None, U128(amount));
+            }
+        };
+    }
+
+    /// Take an extra storage deposit from the red packet reserve, return false if the reserve is short
+    fn internal_take_ft_storage_deposit(&mut self, public_key: &PublicKey, amount: Balance) -> bool {
+        match self.red_packets.get(public_key) {
+            Some(mut red_packet) if red_packet.ft_storage_reserve.0 >= amount => {
+                red_packet.ft_storage_reserve = U128(red_packet.ft_storage_reserve.0 - amount);
+                self.save_red_packet(public_key, &red_packet);
+                true
+            },
+            _ => false
+        }
+    }
+}
@@ -3,9 +3,10 @@ use crate::ContractExt;
 use crate::dynamic_storage_management::DynamicStorageBasic;
 use crate::errors::*;
 use crate::utils::{assert_zero_deposit, transfer};
+use crate::events::{emit_event, EventData, EventKind};
 
 use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
-use near_sdk::{near_bindgen, AccountId, env, assert_one_yocto};
+use near_sdk::{near_bindgen, AccountId, env, assert_one_yocto, require};
 use near_sdk::json_types::U128;
 
 
@@ -50,6 +51,12 @@ impl Contract {
             self.storage_manager.register_account_or_deposit_storage_balance(account_id.clone(), amount);
         };
 
+        emit_event(EventKind::StorageDeposit, vec![EventData {
+            account_id: Some(&account_id),
+            amount: Some(amount.into()),
+            ..Default::default()
+        }]);
+
         self.internal_storage_balance_of(account_id).unwrap()
     }
 
@@ -58,6 +65,11 @@ impl Contract {
 
         let withdraw_amount = self.storage_manager.withdraw_storage_balance(&account_id, amount);
         if withdraw_amount > 0 {
+            emit_event(EventKind::StorageWithdraw, vec![EventData {
+                account_id: Some(&account_id),
+                amount: Some(withdraw_amount.into()),
+                ..Default::default()
+            }]);
             transfer(account_id.clone(), withdraw_amount);
         };
 
@@ -72,8 +84,32 @@ impl Contract {
         self.storage_manager.assert_registration(&account_id);
 
         if self.all_red_packets_run_out(&account_id) || force {
+            require!(!self.has_pending_claims(&account_id), ERR_23_RED_PACKET_HAS_PENDING_CLAIMS);
+            let refunded = if force {
+                self.refund_red_packets(&account_id)
+            } else {
+                vec![]
+            };
             self.clear_red_packets(&account_id, force);
+            self.internal_clear_claims(&account_id);
+            self.approved_funders.remove(&account_id);
             let withdraw_balance = self.storage_manager.unregister_account(&account_id);
+            // followed by refunded amounts per token of live red packets
+            let mut unregister_event_data = vec![EventData {
+                account_id: Some(&account_id),
+                amount: Some(withdraw_balance.into()),
+                ..Default::default()
+            }];
+            for (token, token_id, amount) in &refunded {
+                unregister_event_data.push(EventData {
+                    token: Some(token),
+                    token_id: token_id.as_ref(),
+                    account_id: Some(&account_id),
+                    amount: Some(*amount),
+                    ..Default::default()
+                });
+            };
+            emit_event(EventKind::Unregister, unregister_event_data);
             if withdraw_balance > 0 {
                 transfer(account_id, withdraw_balance);
             };
@@ -0,0 +1,111 @@
+use crate::Contract;
+use crate::enums::Token;
+use crate::errors::*;
+
+use crate::events::{emit_event, EventData, EventKind};
+use crate::dynamic_storage_management::DynamicStorageBasic;
+
+use near_sdk::{AccountId, require};
+use near_sdk::json_types::U128;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Serialize, Deserialize};
+use near_contract_standards::non_fungible_token::TokenId;
+
+
+/// Deposit or failed payout kept in the internal ledger until the account spends or withdraws it
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawableBalance {
+    pub token: Token,
+    pub token_id: Option<AccountId>,
+    pub amount: U128,
+    /// Every failed non fungible token payout is kept as its own balance
+    pub nft_token_id: Option<TokenId>
+}
+
+impl Contract {
+    pub fn internal_credit(&mut self, account_id: &AccountId, token: Token, token_id: Option<AccountId>, amount: U128) {
+        let mut balances = self.ledger.get(account_id).unwrap_or_default();
+        match balances.iter_mut().find(|balance| balance.token == token && balance.token_id == token_id) {
+            Some(balance) => {
+                balance.amount = U128(balance.amount.0 + amount.0);
+            },
+            None => {
+                balances.push(WithdrawableBalance { token: token.clone(), token_id: token_id.clone(), amount, nft_token_id: None });
+            }
+        };
+        self.ledger.insert(account_id, &balances);
+    }
+
+    pub fn internal_credit_nft(&mut self, account_id: &AccountId, token_id: AccountId, nft_token_id: TokenId) {
+        let mut balances = self.ledger.get(account_id).unwrap_or_default();
+        balances.push(WithdrawableBalance {
+            token: Token::NonFungibleToken,
+            token_id: Some(token_id),
+            amount: U128(1),
+            nft_token_id: Some(nft_token_id)
+        });
+        self.ledger.insert(account_id, &balances);
+    }
+
+    pub fn internal_debit_nft(&mut self, account_id: &AccountId, token_id: &AccountId, nft_token_id: &TokenId) {
+        let mut balances = self.ledger.get(account_id).unwrap_or_default();
+        let index = balances
+            .iter()
+            .position(|balance| {
+                balance.token_id.as_ref() == Some(token_id) && balance.nft_token_id.as_ref() == Some(nft_token_id)
+            })
+            .expect(ERR_22_NOT_ENOUGH_WITHDRAWABLE_BALANCE);
+        balances.swap_remove(index);
+        if balances.is_empty() {
+            self.ledger.remove(account_id);
+        } else {
+            self.ledger.insert(account_id, &balances);
+        };
+    }
+
+    /// Deposit into the internal balance of the registered depositor, storage is charged to the depositor,
+    /// so nobody can grow the ledger of another account
+    pub fn internal_deposit(&mut self, account_id: AccountId, token: Token, token_id: Option<AccountId>, amount: U128) {
+        require!(amount.0 > 0 && !matches!(token, Token::NonFungibleToken), ERR_04_INVALID_PARAMETER);
+        self.storage_manager.assert_registration(&account_id);
+
+        emit_event(EventKind::Deposit, vec![EventData {
+            token: Some(&token),
+            token_id: token_id.as_ref(),
+            account_id: Some(&account_id),
+            amount: Some(amount),
+            ..Default::default()
+        }]);
+
+        self.measure_start();
+        self.internal_credit(&account_id, token, token_id, amount);
+        self.measure_end(&account_id);
+
+        self.assert_after_creation(&account_id);
+    }
+
+    /// Return the debited amount, all the balance when `amount` is not given
+    pub fn internal_debit(&mut self, account_id: &AccountId, token: &Token, token_id: &Option<AccountId>, amount: Option<U128>) -> U128 {
+        let mut balances = self.ledger.get(account_id).unwrap_or_default();
+        let index = balances
+            .iter()
+            .position(|balance| balance.token == *token && balance.token_id == *token_id)
+            .expect(ERR_22_NOT_ENOUGH_WITHDRAWABLE_BALANCE);
+        let balance = &mut balances[index];
+        let amount = amount.unwrap_or(balance.amount);
+        if amount.0 == 0 || amount.0 > balance.amount.0 {
+            panic!("{}", ERR_22_NOT_ENOUGH_WITHDRAWABLE_BALANCE);
+        };
+        balance.amount = U128(balance.amount.0 - amount.0);
+        if balance.amount.0 == 0 {
+            balances.swap_remove(index);
+        };
+        if balances.is_empty() {
+            self.ledger.remove(account_id);
+        } else {
+            self.ledger.insert(account_id, &balances);
+        };
+        amount
+    }
+}
@@ -4,6 +4,9 @@ mod utils;
 mod enums;
 mod cross_other;
 mod impl_fungible_token_receiver;
+mod impl_non_fungible_token_receiver;
+mod multi_token_receiver;
+mod impl_multi_token_receiver;
 mod errors;
 mod impl_storage_management;
 mod dynamic_storage_management;
@@ -13,22 +16,35 @@ mod impl_saika_red_packet;
 mod red_packet_view;
 mod saika_red_packet;
 mod impl_saika_red_packet_resolver;
+mod white_list;
+mod events;
+mod bundle_leg;
+mod ledger;
+mod claim_index;
+mod migration;
+mod red_packet_summary;
+mod red_packet_options;
 
 use crate::dynamic_storage_management::DynamicStorageManager;
 use crate::enums::StorageKey;
 use crate::red_packet::RedPacket;
+use crate::ledger::WithdrawableBalance;
+use crate::claim_index::ClaimRecord;
+use crate::red_packet_summary::RedPacketSummary;
 
 use std::collections::HashSet;
-use near_sdk::collections::{UnorderedMap};
+use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
 use near_sdk::{AccountId, PublicKey, PanicOnDefault, near_bindgen, env};
 use near_sdk::borsh::{self, BorshDeserialize,BorshSerialize};
 
+/// Values of `red_packets` are still in the old layout, converted by `migrate_red_packets`
 #[near_bindgen]
 #[derive(BorshDeserialize,BorshSerialize,PanicOnDefault)]
 struct OldContract {
     red_packets: UnorderedMap<PublicKey, RedPacket>,
     owners: UnorderedMap<AccountId, HashSet<PublicKey>>,
-    storage_manager: DynamicStorageManager
+    storage_manager: DynamicStorageManager,
+    helper_contract_id: AccountId
 }
 
 #[near_bindgen]
@@ -37,7 +53,17 @@ struct Contract {
     red_packets: UnorderedMap<PublicKey, RedPacket>,
     owners: UnorderedMap<AccountId, HashSet<PublicKey>>,
     storage_manager: DynamicStorageManager,
-    helper_contract_id: AccountId
+    helper_contract_id: AccountId,
+    /// Failed payouts waiting for withdrawal
+    ledger: LookupMap<AccountId, Vec<WithdrawableBalance>>,
+    /// Claims of registered claimers, every claimer has its own vector
+    claims: LookupMap<AccountId, Vector<ClaimRecord>>,
+    /// Accounts allowed to create red packets owned by the key account
+    approved_funders: LookupMap<AccountId, HashSet<AccountId>>,
+    /// Red packets at the front of `red_packets` not converted to the current layout yet
+    legacy_red_packets: u64,
+    /// Kept in sync with `red_packets` by `save_red_packet` and `drop_red_packet`
+    summaries: LookupMap<PublicKey, RedPacketSummary>
 }
 
 
@@ -50,19 +76,36 @@ impl Contract {
             red_packets: UnorderedMap::new(StorageKey::RedPackets),
             owners: UnorderedMap::new(StorageKey::Owners),
             storage_manager: DynamicStorageManager::new(StorageKey::DynamicStorageManager),
-            helper_contract_id
+            helper_contract_id,
+            ledger: LookupMap::new(StorageKey::Ledger),
+            claims: LookupMap::new(StorageKey::Claims),
+            approved_funders: LookupMap::new(StorageKey::ApprovedFunders),
+            legacy_red_packets: 0,
+            summaries: LookupMap::new(StorageKey::Summaries)
         }
     }
 
+    /// Old red packets can't be read until `migrate_red_packets` converts them
     #[init(ignore_state)]
     #[private]
-    pub fn upgrade(helper_contract_id: AccountId) -> Self {
+    pub fn upgrade() -> Self {
         let contract = env::state_read::<OldContract>().unwrap();
         Self {
+            legacy_red_packets: contract.red_packets.len(),
             red_packets: contract.red_packets,
             owners: contract.owners,
             storage_manager: contract.storage_manager,
-            helper_contract_id
+            helper_contract_id: contract.helper_contract_id,
+            ledger: LookupMap::new(StorageKey::Ledger),
+            claims: LookupMap::new(StorageKey::Claims),
+            approved_funders: LookupMap::new(StorageKey::ApprovedFunders),
+            summaries: LookupMap::new(StorageKey::Summaries)
         }
     }
+
+    /// Convert at most `limit` old red packets after upgrade, return the number of old red packets left
+    #[private]
+    pub fn migrate_red_packets(&mut self, limit: u64) -> u64 {
+        self.internal_migrate_red_packets(limit)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,106 @@
+use crate::Contract;
+use crate::enums::{SplitMod, Token};
+use crate::red_packet::{ClaimLogEntry, RedPacket};
+use crate::red_packet_summary::RedPacketSummary;
+use crate::white_list::WhiteList;
+
+use std::collections::{HashMap, HashSet};
+use near_sdk::{AccountId, PublicKey};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+
+
+/// Layout of red packets stored before the upgrade
+#[derive(BorshDeserialize,BorshSerialize)]
+pub struct OldRedPacket {
+    pub token: Token,
+    pub token_id: Option<AccountId>,
+    pub owner_id: AccountId,
+    pub init_balance: U128,
+    pub current_balance: U128,
+    pub refunded_balance: U128,
+    pub init_split: usize,
+    pub current_split: usize,
+    pub split_mod: SplitMod,
+    pub msg: Option<String>,
+    pub white_list: Option<HashSet<AccountId>>,
+    pub claimers: HashMap<AccountId, U128>,
+    pub failed_claimers: HashMap<AccountId, U128>,
+    pub create_timestamp: U64,
+    pub run_out_timestamp: Option<U64>
+}
+
+impl From<OldRedPacket> for RedPacket {
+    fn from(red_packet: OldRedPacket) -> Self {
+        // claim order and time of old claims are unknown
+        let claim_log = red_packet.claimers
+            .iter()
+            .map(|(account_id, amount)| ClaimLogEntry {
+                account_id: account_id.clone(),
+                amount: *amount,
+                timestamp: U64(0),
+                block_height: U64(0),
+                success: true
+            })
+            .collect();
+        Self {
+            token: red_packet.token,
+            token_id: red_packet.token_id,
+            funder_id: red_packet.owner_id.clone(),
+            owner_id: red_packet.owner_id,
+            init_balance: red_packet.init_balance,
+            current_balance: red_packet.current_balance,
+            refunded_balance: red_packet.refunded_balance,
+            init_split: red_packet.init_split,
+            current_split: red_packet.current_split,
+            split_mod: red_packet.split_mod,
+            allocation: vec![],
+            msg: red_packet.msg,
+            white_list: red_packet.white_list.map(WhiteList::Accounts),
+            claimers: red_packet.claimers,
+            failed_claimers: red_packet.failed_claimers,
+            claim_log,
+            create_timestamp: red_packet.create_timestamp,
+            run_out_timestamp: red_packet.run_out_timestamp,
+            refund_timestamp: None,
+            expire_at: None,
+            open_at: None,
+            top_ups: vec![],
+            nft_token_ids: vec![],
+            claimed_nft_token_ids: HashMap::new(),
+            sealed: true,
+            bundle: vec![],
+            password_hash: None,
+            wrong_password_attempts: HashMap::new(),
+            // the access key of an old red packet is paid by the contract
+            access_key_allowance: U128(0),
+            ft_storage_reserve: U128(0),
+            retryable_claims: false,
+            retry_claims: HashMap::new(),
+            refund_to_balance: false,
+            pending_claims: vec![]
+        }
+    }
+}
+
+impl Contract {
+    /// Convert old red packets from the back of the legacy range at the front of `red_packets`,
+    /// so removing a converted red packet never moves an old one out of the range.
+    /// Summaries are added on the way, storage growth of the conversion is paid by the contract.
+    /// Return the number of old red packets left.
+    pub fn internal_migrate_red_packets(&mut self, limit: u64) -> u64 {
+        for _ in 0..limit {
+            if self.legacy_red_packets == 0 {
+                break;
+            };
+            let index = self.legacy_red_packets - 1;
+            let key_raw = self.red_packets.keys_as_vector().get_raw(index).unwrap();
+            let value_raw = self.red_packets.values_as_vector().get_raw(index).unwrap();
+            let red_packet = RedPacket::from(OldRedPacket::try_from_slice(&value_raw).unwrap());
+            self.red_packets.insert_raw(&key_raw, &red_packet.try_to_vec().unwrap());
+            self.summaries.insert(&PublicKey::try_from_slice(&key_raw).unwrap(), &RedPacketSummary::from(&red_packet));
+            self.legacy_red_packets = index;
+        };
+        self.legacy_red_packets
+    }
+}
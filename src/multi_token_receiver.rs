@@ -0,0 +1,15 @@
+use near_sdk::{AccountId, PromiseOrValue};
+use near_sdk::json_types::U128;
+
+
+/// NEP-245 receiver, not provided by near-contract-standards yet
+pub trait MultiTokenReceiver {
+    fn mt_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_ids: Vec<AccountId>,
+        token_ids: Vec<String>,
+        amounts: Vec<U128>,
+        msg: String
+    ) -> PromiseOrValue<Vec<U128>>;
+}
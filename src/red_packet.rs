@@ -1,13 +1,17 @@
 use crate::constants::*;
 use crate::utils::*;
 use crate::errors::*;
-use crate::enums::{SplitMod, Token};
+use crate::enums::{RedPacketStatus, SplitMod, Token};
+use crate::white_list::{MerkleProof, WhiteList};
+use crate::bundle_leg::BundleLeg;
+use crate::red_packet_options::RedPacketOptions;
 
-use std::collections::{HashMap, HashSet};
-use near_sdk::{AccountId, env};
-use near_sdk::json_types::{U128, U64};
+use std::collections::HashMap;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::{AccountId, Balance, env};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::serde::Serialize;
+use near_sdk::serde::{Serialize, Deserialize};
 
 
 
@@ -17,58 +21,309 @@ pub struct RedPacket {
     pub token: Token,
     pub token_id: Option<AccountId>,
     pub owner_id: AccountId,
+    /// Account paying the balance, may differ from the owner
+    pub funder_id: AccountId,
     pub init_balance: U128,
     pub current_balance: U128,
     pub refunded_balance: U128,
     pub init_split: usize,
     pub current_split: usize,
     pub split_mod: SplitMod,
+    /// Remaining predetermined shares
+    pub allocation: Vec<U128>,
     pub msg: Option<String>,
-    pub white_list: Option<HashSet<AccountId>>,
+    pub white_list: Option<WhiteList>,
+    /// Lookup of current claims, `claim_log` keeps the order and history
     pub claimers: HashMap<AccountId, U128>,
     pub failed_claimers: HashMap<AccountId, U128>,
+    /// Claims in claim order, failed claims are kept and marked
+    pub claim_log: Vec<ClaimLogEntry>,
     pub create_timestamp: U64,
-    pub run_out_timestamp: Option<U64>
+    pub run_out_timestamp: Option<U64>,
+    /// Set when the remaining balance is refunded before running out
+    pub refund_timestamp: Option<U64>,
+    pub expire_at: Option<U64>,
+    pub open_at: Option<U64>,
+    pub top_ups: Vec<TopUp>,
+    /// Remaining non fungible tokens, one token for one split
+    pub nft_token_ids: Vec<TokenId>,
+    pub claimed_nft_token_ids: HashMap<AccountId, TokenId>,
+    /// Can be claimed only after it is sealed, then it takes no more non fungible tokens or bundle legs
+    pub sealed: bool,
+    /// Extra fungible tokens paid out with every claim
+    pub bundle: Vec<BundleLeg>,
+    /// sha256 of the claim password
+    #[serde(skip_serializing)]
+    pub password_hash: Option<Base64VecU8>,
+    /// `claimer_id` is chosen by the caller, so at most `MAX_WRONG_PASSWORD_CLAIMERS` claimers are tracked
+    pub wrong_password_attempts: HashMap<AccountId, WrongPasswordAttempts>,
+    /// NEAR paid for the allowance of the current access key, never refunded
+    /// since the contract can not read how much of it is left
+    pub access_key_allowance: U128,
+    /// NEAR reserved to register claimers on the fungible token contract
+    pub ft_storage_reserve: U128,
+    /// Keep failed fungible token claims escrowed for the claimers instead of refunding the owner
+    pub retryable_claims: bool,
+    pub retry_claims: HashMap<AccountId, RetryClaim>,
+    /// Refunds, failed claims and released reserves stay in the internal balance of the owner
+    pub refund_to_balance: bool,
+    /// Token transfers to claimers waiting for the resolver
+    pub pending_claims: Vec<PendingClaim>
+}
+
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingClaim {
+    pub claimer_id: AccountId,
+    pub token_id: AccountId,
+    pub amount: U128,
+    pub timestamp: U64
+}
+
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RetryClaim {
+    pub amount: U128,
+    /// Escrow goes back to the owner after the deadline
+    pub deadline: U64,
+    pub retrying: bool
+}
+
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WrongPasswordAttempts {
+    /// Wrong attempts since the last lock
+    pub count: u32,
+    pub last_timestamp: U64
+}
+
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TopUp {
+    pub amount: U128,
+    pub split: usize,
+    pub timestamp: U64
+}
+
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize,Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimLogEntry {
+    pub account_id: AccountId,
+    pub amount: U128,
+    pub timestamp: U64,
+    pub block_height: U64,
+    /// Cleared when the transfer to the claimer fails
+    pub success: bool
 }
 
 impl RedPacket {
     pub fn new_valid(
         token: Token,
         token_id: Option<AccountId>,
-        owner_id: AccountId,
+        funder_id: AccountId,
         amount: U128,
         split: usize,
         split_mod: SplitMod,
-        msg: Option<String>,
-        white_list: Option<HashSet<AccountId>>
+        options: RedPacketOptions
     ) -> Result<Self, &'static str> {
-        let red_packet = Self {
+        Self::new(token, token_id, funder_id, amount, split, split_mod, options).validated()
+    }
+
+    /// Non fungible token red packet with its first token, the following tokens are added one by one
+    pub fn new_valid_non_fungible_token(
+        token_id: AccountId,
+        funder_id: AccountId,
+        nft_token_id: TokenId,
+        options: RedPacketOptions
+    ) -> Result<Self, &'static str> {
+        let mut red_packet = Self::new(
+            Token::NonFungibleToken,
+            Some(token_id),
+            funder_id,
+            U128(1),
+            1,
+            SplitMod::Average,
+            options
+        );
+        red_packet.nft_token_ids.push(nft_token_id);
+        red_packet.validated()
+    }
+
+    fn new(
+        token: Token,
+        token_id: Option<AccountId>,
+        funder_id: AccountId,
+        amount: U128,
+        split: usize,
+        split_mod: SplitMod,
+        options: RedPacketOptions
+    ) -> Self {
+        // non fungible tokens arrive one by one
+        let sealed = options.seal.unwrap_or(!matches!(token, Token::NonFungibleToken));
+        let allocation = match &split_mod {
+            SplitMod::Custom(amounts) => amounts.clone(),
+            _ => vec![]
+        };
+        Self {
             token,
             token_id,
-            owner_id,
+            owner_id: options.owner_id(&funder_id),
+            funder_id,
             init_balance: amount,
             current_balance: amount,
             refunded_balance: U128(0),
             init_split: split,
             current_split: split,
             split_mod,
-            msg,
-            white_list,
+            allocation,
+            msg: options.msg,
+            white_list: options.white_list,
             claimers: HashMap::new(),
             failed_claimers: HashMap::new(),
+            claim_log: vec![],
             create_timestamp: U64(env::block_timestamp()),
-            run_out_timestamp: None
+            run_out_timestamp: None,
+            refund_timestamp: None,
+            expire_at: options.expire_at,
+            open_at: options.open_at,
+            top_ups: vec![],
+            nft_token_ids: vec![],
+            claimed_nft_token_ids: HashMap::new(),
+            sealed,
+            bundle: vec![],
+            password_hash: options.password_hash,
+            wrong_password_attempts: HashMap::new(),
+            access_key_allowance: U128(0),
+            ft_storage_reserve: options.ft_storage_reserve.unwrap_or(U128(0)),
+            retryable_claims: options.retryable_claims.unwrap_or(false),
+            retry_claims: HashMap::new(),
+            refund_to_balance: options.refund_to_balance.unwrap_or(false),
+            pending_claims: vec![]
+        }
+    }
+
+    fn validated(mut self) -> Result<Self, &'static str> {
+        if !self.is_valid() {
+            return Err(ERR_04_INVALID_PARAMETER);
         };
-        if !red_packet.is_valid() {
+        if (self.ft_storage_reserve.0 != 0 || self.retryable_claims) && !matches!(self.token, Token::FungibleToken) {
             return Err(ERR_04_INVALID_PARAMETER);
         };
-        Ok(red_packet)
+        if self.refund_to_balance && matches!(self.token, Token::NonFungibleToken) {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        if let SplitMod::RandomPrecomputed = self.split_mod {
+            self.allocation = precompute_random_subs(
+                self.init_balance.0,
+                self.init_split,
+                self.min_sub()
+            );
+        };
+        Ok(self)
+    }
+
+    /// Minimum share of random split mod
+    pub fn min_sub(&self) -> Option<u128> {
+        match self.token {
+            Token::NEAR => Some(ONE_NEAR / 10), // 0.1 NEAR
+            Token::FungibleToken | Token::NonFungibleToken | Token::MultiToken { .. } => None
+        }
     }
 
     pub fn is_run_out(&self) -> bool {
         self.current_split == 0
     }
 
+    pub fn is_expired(&self) -> bool {
+        match self.expire_at {
+            None => false,
+            Some(expire_at) => env::block_timestamp() >= expire_at.0
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        match self.open_at {
+            None => true,
+            Some(open_at) => env::block_timestamp() >= open_at.0
+        }
+    }
+
+    /// Run out without escrowed or pending claims
+    pub fn is_finished(&self) -> bool {
+        self.is_run_out() && self.retry_claims.is_empty() && !self.has_pending_claims()
+    }
+
+    pub fn has_pending_claims(&self) -> bool {
+        !self.pending_claims.is_empty()
+    }
+
+    pub fn add_pending_claim(&mut self, claimer_id: AccountId, token_id: AccountId, amount: U128) {
+        self.pending_claims.push(PendingClaim {
+            claimer_id,
+            token_id,
+            amount,
+            timestamp: env::block_timestamp().into()
+        });
+    }
+
+    /// Drop pending claims whose resolver never ran, the transfers are treated as delivered.
+    /// Return the number of dropped claims.
+    pub fn virtual_clear_stale_pending_claims(&mut self) -> usize {
+        let pending_claims = self.pending_claims.len();
+        self.pending_claims.retain(|pending_claim| {
+            env::block_timestamp() < pending_claim.timestamp.0 + PENDING_CLAIM_TIMEOUT
+        });
+        pending_claims - self.pending_claims.len()
+    }
+
+    pub fn remove_pending_claim(&mut self, claimer_id: &AccountId, token_id: &AccountId) {
+        if let Some(index) = self.pending_claims
+            .iter()
+            .position(|pending_claim| pending_claim.claimer_id == *claimer_id && pending_claim.token_id == *token_id) {
+            self.pending_claims.remove(index);
+        };
+    }
+
+    /// Largest successful claim, the earliest one wins a tie.
+    /// Every claimer of a non fungible token red packet gets one token, so there is no luckiest claimer.
+    pub fn luckiest_claimer(&self) -> Option<ClaimLogEntry> {
+        if let Token::NonFungibleToken = self.token {
+            return None;
+        };
+        self.claim_log
+            .iter()
+            .filter(|entry| entry.success)
+            .fold(None, |luckiest: Option<&ClaimLogEntry>, entry| match luckiest {
+                Some(luckiest) if luckiest.amount.0 >= entry.amount.0 => Some(luckiest),
+                _ => Some(entry)
+            })
+            .cloned()
+    }
+
+    /// Time from creation to the last claim, none when not run out or refunded
+    pub fn time_to_run_out(&self) -> Option<U64> {
+        if self.refund_timestamp.is_some() {
+            return None;
+        };
+        self.run_out_timestamp.map(|run_out_timestamp| U64(run_out_timestamp.0 - self.create_timestamp.0))
+    }
+
+    pub fn is_password_protected(&self) -> bool {
+        self.password_hash.is_some()
+    }
+
+    pub fn status(&self) -> RedPacketStatus {
+        if self.refund_timestamp.is_some() {
+            RedPacketStatus::Refunded
+        } else if self.is_run_out() {
+            RedPacketStatus::RunOut
+        } else if self.is_expired() {
+            RedPacketStatus::Expired
+        } else {
+            RedPacketStatus::Active
+        }
+    }
+
     pub fn is_valid(&self) -> bool {
         match self.token {
             Token::NEAR => {
@@ -76,10 +331,29 @@ impl RedPacket {
                     return false;
                 }
             },
-            Token::FungibleToken => {
+            Token::FungibleToken | Token::MultiToken { .. } => {
                 if self.token_id.is_none() {
                     return false;
                 }
+            },
+            Token::NonFungibleToken => {
+                if self.token_id.is_none() || self.init_split > MAX_NFT_RED_PACKET_SPLIT {
+                    return false;
+                }
+                if !matches!(self.split_mod, SplitMod::Average) {
+                    return false;
+                }
+                if let Some(WhiteList::Amounts(_)) = self.white_list {
+                    return false;
+                }
+                let mut nft_token_ids = self.nft_token_ids.clone();
+                nft_token_ids.sort();
+                nft_token_ids.dedup();
+                if nft_token_ids.len() != self.nft_token_ids.len()
+                    || self.nft_token_ids.len() != self.init_split
+                    || self.init_balance.0 != self.init_split as u128 {
+                    return false;
+                }
             }
         }
 
@@ -89,54 +363,211 @@ impl RedPacket {
         if self.init_balance.0 < self.init_split as u128 {
             return false;
         }
+        if let SplitMod::Random | SplitMod::RandomPrecomputed = self.split_mod {
+            let min_sub = self.min_sub().unwrap_or(1);
+            if self.init_balance.0 < min_sub * self.init_split as u128 {
+                return false;
+            }
+        }
+        if let SplitMod::Custom(amounts) = &self.split_mod {
+            if amounts.len() != self.init_split {
+                return false;
+            }
+            let mut total: u128 = 0;
+            for amount in amounts {
+                if amount.0 == 0 {
+                    return false;
+                }
+                match total.checked_add(amount.0) {
+                    None => return false,
+                    Some(sum) => total = sum
+                }
+            }
+            if total != self.init_balance.0 {
+                return false;
+            }
+        }
         if let Some(msg) = &self.msg {
             if msg.len() > MAX_RED_PACKET_MSG_LEN {
                 return false;
             }
         }
         if let Some(wl) = &self.white_list {
-            if wl.len() != self.init_split as usize {
+            if !wl.is_valid(self.init_split, self.init_balance.0) {
+                return false;
+            }
+            // listed amounts skip the predetermined shares, which are computed against the whole balance
+            if wl.has_amounts() && matches!(self.split_mod, SplitMod::Custom(_) | SplitMod::RandomPrecomputed) {
                 return false;
             }
         }
+        if let Some(password_hash) = &self.password_hash {
+            if password_hash.0.len() != 32 {
+                return false;
+            }
+        }
+        if let Some(expire_at) = self.expire_at {
+            if expire_at.0 <= self.create_timestamp.0 {
+                return false;
+            }
+            if let Some(open_at) = self.open_at {
+                if expire_at.0 <= open_at.0 {
+                    return false;
+                }
+            }
+        }
         true
     }
 
-    pub fn virtual_claim(&mut self, claimer_id: AccountId, min_sub: Option<u128>) -> Result<U128, &'static str> {
+    pub fn access_key_allowance_per_claim(&self) -> Balance {
+        access_key_allowance_per_claim(&self.token, self.bundle.len())
+    }
+
+    /// Release the unused storage reserve, the access key allowance is not released
+    pub fn virtual_release_reserves(&mut self) -> U128 {
+        let released_balance = self.ft_storage_reserve.0;
+        self.ft_storage_reserve = U128(0);
+        U128(released_balance)
+    }
+
+    /// Take up to one storage deposit from the reserve for a claim,
+    /// the resolver adjusts it to the minimum of the token contract
+    pub fn virtual_take_ft_storage_deposit(&mut self) -> Option<U128> {
+        if self.ft_storage_reserve.0 == 0 {
+            return None;
+        };
+        let storage_deposit = std::cmp::min(self.ft_storage_reserve.0, FT_STORAGE_DEPOSIT);
+        self.ft_storage_reserve = U128(self.ft_storage_reserve.0 - storage_deposit);
+        Some(U128(storage_deposit))
+    }
+
+    /// Check the claim password, a wrong attempt is recorded instead of failing
+    /// so that the claimer is locked out after too many wrong attempts
+    pub fn virtual_check_password(
+        &mut self,
+        claimer_id: &AccountId,
+        password: Option<String>
+    ) -> Result<bool, &'static str> {
+        let password_hash = match &self.password_hash {
+            None => return Ok(true),
+            Some(password_hash) => password_hash
+        };
+
+        let now = env::block_timestamp();
+        if let Some(attempts) = self.wrong_password_attempts.get(claimer_id) {
+            if attempts.count >= MAX_WRONG_PASSWORD_ATTEMPTS
+                && now < attempts.last_timestamp.0 + WRONG_PASSWORD_LOCK_DURATION {
+                return Err(ERR_17_TOO_MANY_WRONG_PASSWORD_ATTEMPTS);
+            }
+        };
+
+        let matched = match password {
+            None => false,
+            Some(password) => env::sha256(password.as_bytes()) == password_hash.0
+        };
+        if matched {
+            self.wrong_password_attempts.remove(claimer_id);
+        } else {
+            if !self.wrong_password_attempts.contains_key(claimer_id) {
+                self.make_room_for_wrong_password_claimer(now);
+            };
+            let attempts = self.wrong_password_attempts
+                .entry(claimer_id.clone())
+                .or_insert(WrongPasswordAttempts { count: 0, last_timestamp: U64(now) });
+            if attempts.count >= MAX_WRONG_PASSWORD_ATTEMPTS {
+                // lock expired
+                attempts.count = 0;
+            };
+            attempts.count += 1;
+            attempts.last_timestamp = U64(now);
+        };
+        Ok(matched)
+    }
+
+    /// Forget claimers whose last wrong attempt is older than the lock,
+    /// evict the earliest one if all of them are recent
+    fn make_room_for_wrong_password_claimer(&mut self, now: u64) {
+        if self.wrong_password_attempts.len() < MAX_WRONG_PASSWORD_CLAIMERS {
+            return;
+        };
+        self.wrong_password_attempts
+            .retain(|_, attempts| now < attempts.last_timestamp.0 + WRONG_PASSWORD_LOCK_DURATION);
+        if self.wrong_password_attempts.len() < MAX_WRONG_PASSWORD_CLAIMERS {
+            return;
+        };
+        let earliest_claimer_id = self.wrong_password_attempts
+            .iter()
+            .min_by_key(|(_, attempts)| attempts.last_timestamp.0)
+            .map(|(claimer_id, _)| claimer_id.clone())
+            .unwrap();
+        self.wrong_password_attempts.remove(&earliest_claimer_id);
+    }
+
+    pub fn virtual_claim(
+        &mut self,
+        claimer_id: AccountId,
+        min_sub: Option<u128>,
+        merkle_proof: Option<MerkleProof>
+    ) -> Result<U128, &'static str> {
         if self.is_run_out() {
             return Ok(U128(0));
         };
 
+        if self.is_expired() {
+            return Err(ERR_06_RED_PACKET_EXPIRED);
+        }
+
+        if !self.is_open() {
+            return Err(ERR_10_RED_PACKET_NOT_OPEN);
+        }
+
+        if !self.sealed {
+            return Err(ERR_27_RED_PACKET_NOT_SEALED);
+        }
+
         if self.claimers.contains_key(&claimer_id) {
             return Err(ERR_07_NO_DOUBLE_CLAIM);
         }
 
+        let mut listed_amount = None;
         if let Some(wl) = &mut self.white_list {
-            if !wl.contains(&claimer_id) {
-                return Err(ERR_08_CLAIMER_NOT_IN_WHITE_LIST);
-            } else {
-                wl.remove(&claimer_id);
-            }
+            listed_amount = wl.remove(&claimer_id, merkle_proof)?;
         };
 
-        let claim_amount: u128;
-
-        match self.split_mod {
-            SplitMod::Average => {
-                claim_amount = average_sub(
-                    self.current_balance.0,
-                    self.current_split
-                );
+        let claim_amount = match listed_amount {
+            // every claimer gets one non fungible token
+            Some(amount) if !matches!(self.token, Token::NonFungibleToken) => {
+                // keep enough balance for the rest splits, which may be computed by split mod
+                let min_share = match (&self.white_list, &self.split_mod) {
+                    (Some(WhiteList::MerkleRoot(_)), SplitMod::Random) => min_sub.unwrap_or(1),
+                    _ => 1
+                };
+                let reserved = min_share * (self.current_split as u128 - 1);
+                if amount.0 == 0 || amount.0 > self.current_balance.0.saturating_sub(reserved) {
+                    return Err(ERR_12_NOT_ENOUGH_RED_PACKET_BALANCE);
+                }
+                amount.0
             },
-            SplitMod::Random => {
-                claim_amount = random_sub(
-                    self.current_balance.0,
-                    self.current_split,
-                    min_sub
-                );
-            }
+            _ => self.split_sub(min_sub)
+        };
+
+        for (index, leg) in self.bundle.iter_mut().enumerate() {
+            leg.virtual_claim(claimer_id.clone(), self.current_split, index as u128);
         };
 
+        if let Token::NonFungibleToken = self.token {
+            let index = gen_range(0, self.nft_token_ids.len() as u128) as usize;
+            let nft_token_id = self.nft_token_ids.swap_remove(index);
+            self.claimed_nft_token_ids.insert(claimer_id.clone(), nft_token_id);
+        };
+
+        self.claim_log.push(ClaimLogEntry {
+            account_id: claimer_id.clone(),
+            amount: claim_amount.into(),
+            timestamp: U64(env::block_timestamp()),
+            block_height: U64(env::block_height()),
+            success: true
+        });
         self.claimers.insert(claimer_id, claim_amount.into());
         self.current_balance.0 -= claim_amount;
         self.current_split -= 1;
@@ -148,27 +579,276 @@ impl RedPacket {
         Ok(claim_amount.into())
     }
 
-    pub fn virtual_refund(&mut self, owner_id: AccountId) -> Result<U128, &'static str> {
+    fn split_sub(&mut self, min_sub: Option<u128>) -> u128 {
+        match self.split_mod {
+            SplitMod::Average => {
+                average_sub(
+                    self.current_balance.0,
+                    self.current_split
+                )
+            },
+            SplitMod::Random => {
+                random_sub(
+                    self.current_balance.0,
+                    self.current_split,
+                    min_sub
+                )
+            },
+            // the allocation is public, pick a share at claim time so the next one can not be foreseen
+            SplitMod::Custom(_) | SplitMod::RandomPrecomputed => {
+                let index = gen_range(0, self.allocation.len() as u128) as usize;
+                self.allocation.swap_remove(index).0
+            }
+        }
+    }
+
+    /// Anyone can refund an expired red packet, the balance always goes back to the owner
+    pub fn virtual_refund(&mut self, operator_id: AccountId) -> Result<U128, &'static str> {
         if self.is_run_out() {
             return Ok(U128(0));
         };
 
-        if self.owner_id != owner_id {
+        if self.owner_id != operator_id && !self.is_expired() {
             return Err(ERR_02_NO_PERMISSION_TO_RED_PACKET);
         };
 
-        self.refunded_balance.0 += self.current_balance.0;
+        let refund_amount = self.current_balance;
+        self.refunded_balance.0 += refund_amount.0;
         self.current_balance = U128(0);
+        for leg in self.bundle.iter_mut() {
+            leg.virtual_refund();
+        };
         self.current_split = 0;
+        self.allocation.clear();
+        self.nft_token_ids.clear();
         if let Some(wl) = &mut self.white_list {
             wl.clear();
         };
         self.run_out_timestamp = Some(U64(env::block_timestamp()));
+        self.refund_timestamp = self.run_out_timestamp;
+
+        Ok(refund_amount)
+    }
+
+    /// Add balance and splits to a live red packet.
+    /// Red packet with custom split mod or listed white list can not be topped up.
+    pub fn virtual_top_up(&mut self, operator_id: AccountId, amount: U128, extra_split: usize) -> Result<(), &'static str> {
+        if self.owner_id != operator_id {
+            return Err(ERR_02_NO_PERMISSION_TO_RED_PACKET);
+        };
+        if self.is_run_out() {
+            return Err(ERR_13_RED_PACKET_RUN_OUT);
+        };
+        if self.is_expired() {
+            return Err(ERR_06_RED_PACKET_EXPIRED);
+        };
+        if let SplitMod::Custom(_) = self.split_mod {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        if let Token::NonFungibleToken = self.token {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        if !self.bundle.is_empty() {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        if let Some(WhiteList::Accounts(_) | WhiteList::Amounts(_)) = self.white_list {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        if amount.0 < extra_split as u128 || self.init_split + extra_split > MAX_RED_PACKET_SPLIT {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+
+        self.init_balance.0 += amount.0;
+        self.current_balance.0 += amount.0;
+        self.init_split += extra_split;
+        self.current_split += extra_split;
+
+        if let SplitMod::Random | SplitMod::RandomPrecomputed = self.split_mod {
+            let min_sub = self.min_sub().unwrap_or(1);
+            if self.current_balance.0 < min_sub * self.current_split as u128 {
+                return Err(ERR_04_INVALID_PARAMETER);
+            }
+        };
+        if let SplitMod::RandomPrecomputed = self.split_mod {
+            self.allocation = precompute_random_subs(
+                self.current_balance.0,
+                self.current_split,
+                self.min_sub()
+            );
+        };
 
-        Ok(self.refunded_balance)
+        self.top_ups.push(TopUp {
+            amount,
+            split: extra_split,
+            timestamp: U64(env::block_timestamp())
+        });
+
+        Ok(())
+    }
+
+    /// Add one more non fungible token to a live red packet, both owner and funder can add
+    pub fn virtual_add_nft(&mut self, operator_id: AccountId, nft_token_id: TokenId) -> Result<(), &'static str> {
+        if self.owner_id != operator_id && self.funder_id != operator_id {
+            return Err(ERR_02_NO_PERMISSION_TO_RED_PACKET);
+        };
+        if self.is_run_out() {
+            return Err(ERR_13_RED_PACKET_RUN_OUT);
+        };
+        if self.is_expired() {
+            return Err(ERR_06_RED_PACKET_EXPIRED);
+        };
+        if let Some(WhiteList::Accounts(_) | WhiteList::Amounts(_)) = self.white_list {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        if self.sealed {
+            return Err(ERR_28_RED_PACKET_SEALED);
+        };
+        if self.init_split + 1 > MAX_NFT_RED_PACKET_SPLIT || self.nft_token_ids.contains(&nft_token_id) {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+
+        self.nft_token_ids.push(nft_token_id);
+        self.init_balance.0 += 1;
+        self.current_balance.0 += 1;
+        self.init_split += 1;
+        self.current_split += 1;
+
+        self.top_ups.push(TopUp {
+            amount: U128(1),
+            split: 1,
+            timestamp: U64(env::block_timestamp())
+        });
+
+        Ok(())
+    }
+
+    /// Mark a non fungible token or bundle red packet complete, both owner and funder can seal
+    pub fn virtual_seal(&mut self, operator_id: AccountId) -> Result<(), &'static str> {
+        if self.owner_id != operator_id && self.funder_id != operator_id {
+            return Err(ERR_02_NO_PERMISSION_TO_RED_PACKET);
+        };
+        if self.sealed {
+            return Err(ERR_28_RED_PACKET_SEALED);
+        };
+        self.sealed = true;
+        Ok(())
+    }
+
+    /// Listed amounts of merkle root white list may not use up the balance,
+    /// refund the leftover after red packet runs out
+    pub fn virtual_refund_leftover(&mut self) -> U128 {
+        if !self.is_run_out() {
+            return U128(0);
+        };
+
+        let leftover = self.current_balance;
+        self.refunded_balance.0 += leftover.0;
+        self.current_balance = U128(0);
+
+        leftover
+    }
+
+    /// Add an extra fungible token to an unsealed red packet, both owner and funder can add
+    pub fn virtual_add_bundle_leg(
+        &mut self,
+        operator_id: AccountId,
+        token_id: AccountId,
+        amount: U128,
+        split_mod: SplitMod
+    ) -> Result<(), &'static str> {
+        if self.owner_id != operator_id && self.funder_id != operator_id {
+            return Err(ERR_02_NO_PERMISSION_TO_RED_PACKET);
+        };
+        if self.is_run_out() {
+            return Err(ERR_13_RED_PACKET_RUN_OUT);
+        };
+        if self.is_expired() {
+            return Err(ERR_06_RED_PACKET_EXPIRED);
+        };
+        if let Token::NonFungibleToken = self.token {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        if self.sealed {
+            return Err(ERR_28_RED_PACKET_SEALED);
+        };
+        // every claim pays all legs in one transaction
+        if self.bundle.len() >= MAX_BUNDLE_LEGS
+            || claim_gas(&self.token, self.bundle.len() + 1).0 > MAX_GAS_FOR_CLAIM.0
+            || self.token_id.as_ref() == Some(&token_id)
+            || self.bundle.iter().any(|leg| leg.token_id == token_id) {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+
+        let leg = BundleLeg::new(token_id, amount, split_mod);
+        if !leg.is_valid(self.current_split) {
+            return Err(ERR_04_INVALID_PARAMETER);
+        };
+        self.bundle.push(leg);
+
+        Ok(())
+    }
+
+    pub fn bundle_leg_mut(&mut self, token_id: &AccountId) -> Option<&mut BundleLeg> {
+        self.bundle.iter_mut().find(|leg| leg.token_id == *token_id)
+    }
+
+    /// Escrow a failed claim for the claimer, who keeps the claim slot
+    pub fn virtual_escrow_failed_claim(&mut self, claimer_id: AccountId, failed_amount: U128) {
+        let retry_claim = self.retry_claims
+            .entry(claimer_id)
+            .or_insert(RetryClaim {
+                amount: U128(0),
+                deadline: U64(env::block_timestamp() + RETRY_CLAIM_DURATION),
+                retrying: false
+            });
+        retry_claim.amount = failed_amount;
+        retry_claim.retrying = false;
+    }
+
+    pub fn virtual_retry_claim(&mut self, claimer_id: &AccountId) -> Result<U128, &'static str> {
+        let retry_claim = self.retry_claims
+            .get_mut(claimer_id)
+            .ok_or(ERR_19_NO_RETRY_CLAIM)?;
+        if retry_claim.retrying {
+            return Err(ERR_21_RETRY_CLAIM_IN_PROGRESS);
+        };
+        if env::block_timestamp() >= retry_claim.deadline.0 {
+            return Err(ERR_20_RETRY_CLAIM_EXPIRED);
+        };
+        retry_claim.retrying = true;
+        Ok(retry_claim.amount)
+    }
+
+    pub fn finish_retry_claim(&mut self, claimer_id: &AccountId, success: bool) {
+        if success {
+            self.retry_claims.remove(claimer_id);
+        } else if let Some(retry_claim) = self.retry_claims.get_mut(claimer_id) {
+            retry_claim.retrying = false;
+        };
+    }
+
+    /// Give up escrowed claims which are not retrying, only expired ones unless `all` is set,
+    /// return the refunded claimers and the total amount
+    pub fn virtual_refund_retry_claims(&mut self, all: bool) -> (Vec<AccountId>, U128) {
+        let now = env::block_timestamp();
+        let refund_claimers: Vec<AccountId> = self.retry_claims
+            .iter()
+            .filter(|(_, retry_claim)| !retry_claim.retrying && (all || now >= retry_claim.deadline.0))
+            .map(|(claimer_id, _)| claimer_id.clone())
+            .collect();
+        let mut refund_amount = 0;
+        for claimer_id in &refund_claimers {
+            let retry_claim = self.retry_claims.remove(claimer_id).unwrap();
+            self.failed_claimer(claimer_id.clone(), retry_claim.amount);
+            refund_amount += retry_claim.amount.0;
+        };
+        (refund_claimers, U128(refund_amount))
     }
 
     pub fn failed_claimer(&mut self, claimer_id: AccountId, failed_amount: U128) {
+        if let Some(entry) = self.claim_log.iter_mut().rev().find(|entry| entry.account_id == claimer_id) {
+            entry.success = false;
+        };
         self.claimers.remove(&claimer_id);
         self.failed_claimers.insert(claimer_id, failed_amount);
         self.refunded_balance.0 += failed_amount.0;
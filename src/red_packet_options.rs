@@ -0,0 +1,39 @@
+use crate::white_list::WhiteList;
+
+use near_sdk::AccountId;
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde::{Serialize, Deserialize};
+
+
+/// Optional settings of a new red packet, shared by `create_near_red_packet`,
+/// `create_red_packet_from_balance` and the receiver messages, which take them flattened
+#[derive(Serialize,Deserialize,Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RedPacketOptions {
+    pub msg: Option<String>,
+    pub white_list: Option<WhiteList>,
+    pub expire_at: Option<U64>,
+    pub open_at: Option<U64>,
+    /// Defaults to the funder
+    pub owner_id: Option<AccountId>,
+    pub password_hash: Option<Base64VecU8>,
+    /// NEAR paid for the access key, defaults to the gas of every split
+    pub allowance: Option<U128>,
+    /// NEAR paid from storage balance to register claimers on the token contract, fungible token only
+    pub ft_storage_reserve: Option<U128>,
+    /// Keep failed claims escrowed for `retry_claim`, fungible token only
+    pub retryable_claims: Option<bool>,
+    /// Refunds, failed claims and released reserves stay in the internal balance of the owner,
+    /// not for non fungible token
+    pub refund_to_balance: Option<bool>,
+    /// Unsealed red packet can not be claimed until the owner or funder seals it.
+    /// Non fungible token red packet is unsealed by default and this seals it with the sent token,
+    /// others are sealed by default and `false` leaves them open for bundle legs
+    pub seal: Option<bool>
+}
+
+impl RedPacketOptions {
+    pub fn owner_id(&self, funder_id: &AccountId) -> AccountId {
+        self.owner_id.clone().unwrap_or_else(|| funder_id.clone())
+    }
+}
@@ -0,0 +1,94 @@
+use crate::Contract;
+use crate::enums::{RedPacketStatus, Token};
+use crate::red_packet::RedPacket;
+
+use near_sdk::{AccountId, env, PublicKey};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+
+
+/// Fixed size fields of a red packet, stored apart so owner views can filter and summarize
+/// without reading claimers, white list and other per account records
+#[derive(BorshDeserialize,BorshSerialize)]
+pub struct RedPacketSummary {
+    pub token: Token,
+    pub token_id: Option<AccountId>,
+    pub owner_id: AccountId,
+    pub funder_id: AccountId,
+    pub init_balance: U128,
+    pub current_balance: U128,
+    pub refunded_balance: U128,
+    pub init_split: usize,
+    pub current_split: usize,
+    pub msg: Option<String>,
+    pub has_white_list: bool,
+    pub claimer_count: usize,
+    pub create_timestamp: U64,
+    pub run_out_timestamp: Option<U64>,
+    pub refund_timestamp: Option<U64>,
+    pub expire_at: Option<U64>,
+    pub open_at: Option<U64>,
+    pub bundle_len: usize,
+    pub is_password_protected: bool
+}
+
+impl From<&RedPacket> for RedPacketSummary {
+    fn from(red_packet: &RedPacket) -> Self {
+        Self {
+            token: red_packet.token.clone(),
+            token_id: red_packet.token_id.clone(),
+            owner_id: red_packet.owner_id.clone(),
+            funder_id: red_packet.funder_id.clone(),
+            init_balance: red_packet.init_balance,
+            current_balance: red_packet.current_balance,
+            refunded_balance: red_packet.refunded_balance,
+            init_split: red_packet.init_split,
+            current_split: red_packet.current_split,
+            msg: red_packet.msg.clone(),
+            has_white_list: red_packet.white_list.is_some(),
+            claimer_count: red_packet.claimers.len(),
+            create_timestamp: red_packet.create_timestamp,
+            run_out_timestamp: red_packet.run_out_timestamp,
+            refund_timestamp: red_packet.refund_timestamp,
+            expire_at: red_packet.expire_at,
+            open_at: red_packet.open_at,
+            bundle_len: red_packet.bundle.len(),
+            is_password_protected: red_packet.is_password_protected()
+        }
+    }
+}
+
+impl RedPacketSummary {
+    /// Same as `RedPacket::status`
+    pub fn status(&self) -> RedPacketStatus {
+        if self.refund_timestamp.is_some() {
+            RedPacketStatus::Refunded
+        } else if self.current_split == 0 {
+            RedPacketStatus::RunOut
+        } else if self.is_expired() {
+            RedPacketStatus::Expired
+        } else {
+            RedPacketStatus::Active
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        match self.expire_at {
+            None => false,
+            Some(expire_at) => env::block_timestamp() >= expire_at.0
+        }
+    }
+}
+
+impl Contract {
+    /// Every write of a red packet goes through here to keep its summary in sync
+    pub fn save_red_packet(&mut self, public_key: &PublicKey, red_packet: &RedPacket) {
+        self.red_packets.insert(public_key, red_packet);
+        self.summaries.insert(public_key, &RedPacketSummary::from(red_packet));
+    }
+
+    pub fn drop_red_packet(&mut self, public_key: &PublicKey) {
+        self.red_packets.remove(public_key);
+        self.summaries.remove(public_key);
+    }
+}
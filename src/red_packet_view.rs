@@ -1,7 +1,12 @@
-use crate::enums::{SplitMod, Token};
-use crate::RedPacket;
+use crate::enums::{RedPacketStatus, SplitMod, Token};
+use crate::red_packet::{ClaimLogEntry, PendingClaim, RedPacket, RetryClaim, TopUp};
+use crate::red_packet_summary::RedPacketSummary;
+use crate::white_list::WhiteList;
+use crate::bundle_leg::BundleLeg;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::mem::discriminant;
+use near_contract_standards::non_fungible_token::TokenId;
 use near_sdk::{AccountId, PublicKey};
 use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Serialize,Deserialize};
@@ -14,39 +19,176 @@ pub struct RedPacketView {
     pub token: Token,
     pub token_id: Option<AccountId>,
     pub owner_id: AccountId,
+    pub funder_id: AccountId,
     pub init_balance: U128,
     pub current_balance: U128,
     pub refunded_balance: U128,
     pub init_split: usize,
     pub current_split: usize,
     pub split_mod: SplitMod,
+    pub allocation: Vec<U128>,
     pub msg: Option<String>,
-    pub white_list: Option<HashSet<AccountId>>,
+    pub white_list: Option<WhiteList>,
     pub claimers: HashMap<AccountId, U128>,
     pub failed_claimers: HashMap<AccountId, U128>,
+    pub claim_log: Vec<ClaimLogEntry>,
+    pub luckiest_claimer: Option<ClaimLogEntry>,
     pub create_timestamp: U64,
     pub run_out_timestamp: Option<U64>,
-    pub is_run_out: bool
+    pub refund_timestamp: Option<U64>,
+    pub time_to_run_out: Option<U64>,
+    pub expire_at: Option<U64>,
+    pub open_at: Option<U64>,
+    pub top_ups: Vec<TopUp>,
+    pub nft_token_ids: Vec<TokenId>,
+    pub claimed_nft_token_ids: HashMap<AccountId, TokenId>,
+    pub sealed: bool,
+    pub bundle: Vec<BundleLeg>,
+    pub is_password_protected: bool,
+    pub access_key_allowance: U128,
+    pub ft_storage_reserve: U128,
+    pub retryable_claims: bool,
+    pub retry_claims: HashMap<AccountId, RetryClaim>,
+    pub refund_to_balance: bool,
+    pub pending_claims: Vec<PendingClaim>,
+    pub is_run_out: bool,
+    pub status: RedPacketStatus
 }
 
 pub fn parse_red_packet_view(red_packet: RedPacket, public_key: PublicKey) -> RedPacketView {
     RedPacketView {
         public_key,
+        is_password_protected: red_packet.is_password_protected(),
         is_run_out: red_packet.is_run_out(),
+        status: red_packet.status(),
+        luckiest_claimer: red_packet.luckiest_claimer(),
+        time_to_run_out: red_packet.time_to_run_out(),
         token: red_packet.token,
         token_id: red_packet.token_id,
         owner_id: red_packet.owner_id,
+        funder_id: red_packet.funder_id,
         init_balance: red_packet.init_balance,
         current_balance: red_packet.current_balance,
         refunded_balance: red_packet.refunded_balance,
         init_split: red_packet.init_split,
         current_split: red_packet.current_split,
         split_mod: red_packet.split_mod,
+        allocation: red_packet.allocation,
         msg: red_packet.msg,
         white_list: red_packet.white_list,
         claimers: red_packet.claimers,
         failed_claimers: red_packet.failed_claimers,
+        claim_log: red_packet.claim_log,
         create_timestamp:red_packet.create_timestamp,
-        run_out_timestamp: red_packet.run_out_timestamp
+        run_out_timestamp: red_packet.run_out_timestamp,
+        refund_timestamp: red_packet.refund_timestamp,
+        expire_at: red_packet.expire_at,
+        open_at: red_packet.open_at,
+        top_ups: red_packet.top_ups,
+        nft_token_ids: red_packet.nft_token_ids,
+        claimed_nft_token_ids: red_packet.claimed_nft_token_ids,
+        sealed: red_packet.sealed,
+        bundle: red_packet.bundle,
+        access_key_allowance: red_packet.access_key_allowance,
+        ft_storage_reserve: red_packet.ft_storage_reserve,
+        retryable_claims: red_packet.retryable_claims,
+        retry_claims: red_packet.retry_claims,
+        refund_to_balance: red_packet.refund_to_balance,
+        pending_claims: red_packet.pending_claims
     }
-}
\ No newline at end of file
+}
+
+/// Red packet without `claimers`, `white_list` and other per account records
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RedPacketSummaryView {
+    pub public_key: PublicKey,
+    pub token: Token,
+    pub token_id: Option<AccountId>,
+    pub owner_id: AccountId,
+    pub funder_id: AccountId,
+    pub init_balance: U128,
+    pub current_balance: U128,
+    pub refunded_balance: U128,
+    pub init_split: usize,
+    pub current_split: usize,
+    pub msg: Option<String>,
+    pub has_white_list: bool,
+    pub claimer_count: usize,
+    pub create_timestamp: U64,
+    pub run_out_timestamp: Option<U64>,
+    pub refund_timestamp: Option<U64>,
+    pub expire_at: Option<U64>,
+    pub open_at: Option<U64>,
+    pub bundle_len: usize,
+    pub is_password_protected: bool,
+    pub status: RedPacketStatus
+}
+
+pub fn parse_red_packet_summary_view(summary: RedPacketSummary, public_key: PublicKey) -> RedPacketSummaryView {
+    RedPacketSummaryView {
+        public_key,
+        status: summary.status(),
+        is_password_protected: summary.is_password_protected,
+        has_white_list: summary.has_white_list,
+        claimer_count: summary.claimer_count,
+        bundle_len: summary.bundle_len,
+        token: summary.token,
+        token_id: summary.token_id,
+        owner_id: summary.owner_id,
+        funder_id: summary.funder_id,
+        init_balance: summary.init_balance,
+        current_balance: summary.current_balance,
+        refunded_balance: summary.refunded_balance,
+        init_split: summary.init_split,
+        current_split: summary.current_split,
+        msg: summary.msg,
+        create_timestamp: summary.create_timestamp,
+        run_out_timestamp: summary.run_out_timestamp,
+        refund_timestamp: summary.refund_timestamp,
+        expire_at: summary.expire_at,
+        open_at: summary.open_at
+    }
+}
+
+/// Conditions of owner views, every given condition must match
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RedPacketFilter {
+    /// Matched by kind, any token id of a multi token
+    pub token: Option<Token>,
+    pub token_id: Option<AccountId>,
+    pub status: Option<RedPacketStatus>,
+    /// Inclusive range of `create_timestamp`
+    pub created_from: Option<U64>,
+    pub created_to: Option<U64>
+}
+
+impl RedPacketFilter {
+    pub fn matches(&self, summary: &RedPacketSummary) -> bool {
+        if let Some(token) = &self.token {
+            if discriminant(token) != discriminant(&summary.token) {
+                return false;
+            };
+        };
+        if self.token_id.is_some() && self.token_id != summary.token_id {
+            return false;
+        };
+        if let Some(status) = &self.status {
+            if *status != summary.status() {
+                return false;
+            };
+        };
+        if let Some(created_from) = self.created_from {
+            if summary.create_timestamp.0 < created_from.0 {
+                return false;
+            };
+        };
+        if let Some(created_to) = self.created_to {
+            if summary.create_timestamp.0 > created_to.0 {
+                return false;
+            };
+        };
+        true
+    }
+}
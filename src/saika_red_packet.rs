@@ -1,9 +1,14 @@
-use crate::enums::SplitMod;
-use crate::red_packet_view::RedPacketView;
+use crate::enums::{SplitMod, Token};
+use crate::ledger::WithdrawableBalance;
+use crate::claim_index::ClaimRecord;
+use crate::red_packet_view::{RedPacketFilter, RedPacketSummaryView, RedPacketView};
+use crate::red_packet_options::RedPacketOptions;
+use crate::white_list::MerkleProof;
 
 use std::collections::HashSet;
 use near_sdk::{AccountId, Promise, PublicKey};
 use near_sdk::json_types::U128;
+use near_contract_standards::non_fungible_token::TokenId;
 
 
 pub trait SaikaRedPacket {
@@ -12,24 +17,81 @@ pub trait SaikaRedPacket {
         public_key: PublicKey,
         split: usize,
         split_mod: SplitMod,
-        msg: Option<String>,
-        white_list: Option<HashSet<AccountId>>
+        options: Option<RedPacketOptions>
     );
 
-    fn claim_red_packet(&mut self, claimer_id: AccountId) -> U128;
+    fn top_up_red_packet(&mut self, public_key: PublicKey, extra_split: usize);
+
+    fn seal_red_packet(&mut self, public_key: PublicKey);
+
+    fn claim_red_packet(
+        &mut self,
+        claimer_id: AccountId,
+        merkle_proof: Option<MerkleProof>,
+        password: Option<String>
+    ) -> U128;
 
     fn refund(&mut self, public_key: PublicKey) -> U128;
 
+    fn retry_claim(&mut self, public_key: PublicKey) -> U128;
+
+    fn refund_expired_retry_claims(&mut self, public_key: PublicKey) -> U128;
+
+    fn deposit(&mut self);
+
+    fn create_red_packet_from_balance(
+        &mut self,
+        balance: WithdrawableBalance,
+        public_key: PublicKey,
+        split: usize,
+        split_mod: SplitMod,
+        options: Option<RedPacketOptions>
+    );
+
+    fn withdraw(&mut self, token: Token, token_id: Option<AccountId>, amount: Option<U128>) -> U128;
+
+    fn withdraw_nft(&mut self, token_id: AccountId, nft_token_id: TokenId);
+
+    fn transfer_red_packet_ownership(&mut self, public_key: PublicKey, new_owner_id: AccountId);
+
+    fn approve_funder(&mut self, funder_id: AccountId);
+
+    fn revoke_funder(&mut self, funder_id: AccountId);
+
     fn remove_history(&mut self, public_key: PublicKey);
 
     fn clear_history(&mut self);
 
+    fn clear_stale_pending_claims(&mut self, public_key: PublicKey) -> usize;
+
     fn get_red_packets_by_owner_id(&self, owner_id: AccountId) -> Vec<RedPacketView>;
 
+    fn get_red_packets_by_owner_id_paginated(
+        &self,
+        owner_id: AccountId,
+        filter: Option<RedPacketFilter>,
+        from_index: Option<u64>,
+        limit: Option<u64>
+    ) -> Vec<RedPacketView>;
+
+    fn get_red_packet_summaries_by_owner_id(
+        &self,
+        owner_id: AccountId,
+        filter: Option<RedPacketFilter>,
+        from_index: Option<u64>,
+        limit: Option<u64>
+    ) -> Vec<RedPacketSummaryView>;
+
     fn get_pks_by_owner_id(&self, owner_id: AccountId) -> HashSet<PublicKey>;
 
+    fn get_approved_funders(&self, owner_id: AccountId) -> HashSet<AccountId>;
+
     fn get_red_packet_by_pk(&self, public_key: PublicKey) -> Option<RedPacketView>;
 
+    fn get_withdrawable_balances(&self, account_id: AccountId) -> Vec<WithdrawableBalance>;
+
+    fn get_claims_by_account(&self, account_id: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<ClaimRecord>;
+
     fn get_key_balance(&self, key: PublicKey) -> U128;
 
     fn create_account_and_claim(&mut self, new_account_id: AccountId, new_public_key: PublicKey) -> Promise;
@@ -1,10 +1,14 @@
-use crate::cross_other::ext_ft;
+use crate::cross_other::{ext_ft, ext_nft, ext_mt};
 use crate::cross_self::ext_self;
 use crate::constants::*;
+use crate::enums::Token;
+use crate::errors::*;
+use crate::red_packet::RedPacket;
 
 use std::cmp::min;
-use near_sdk::{AccountId, Balance, env, Promise, PublicKey, require};
+use near_sdk::{AccountId, Balance, env, Gas, Promise, PublicKey, require};
 use near_sdk::json_types::U128;
+use near_contract_standards::non_fungible_token::TokenId;
 
 
 pub fn assert_zero_deposit(amount: Balance) {
@@ -18,15 +22,40 @@ pub fn average_sub(number: u128, split: usize) -> u128 {
 }
 
 pub fn random_sub(number: u128, split: usize, min_sub: Option<u128>) -> u128 {
+    let (min_sub, max_sub) = random_sub_range(number, split, min_sub);
+    gen_range(min_sub, max_sub + 1)
+}
+
+/// Return the inclusive range (min_sub, max_sub) of a random sub
+pub fn random_sub_range(number: u128, split: usize, min_sub: Option<u128>) -> (u128, u128) {
     // The closer min_sub gets to 0, the fairer it is
     let min_sub = min_sub.unwrap_or(1);
     let split = u128::try_from(split).unwrap();
     require!(number >= split * min_sub, "number must >= split * min_sub, default min_sub == 1");
     if split == 1 {
-        return number;
+        return (number, number);
     };
     let max_sub = min(number - min_sub * (split - 1), 2 * (number / split));
-    gen_range(min_sub, max_sub + 1)
+    (min_sub, max_sub)
+}
+
+/// Generate all random subs at once and shuffle them,
+/// so the expected value of every position is the same
+pub fn precompute_random_subs(number: u128, split: usize, min_sub: Option<u128>) -> Vec<U128> {
+    let mut subs: Vec<U128> = Vec::with_capacity(split);
+    let mut rest = number;
+    for i in 0..split {
+        let (min_sub, max_sub) = random_sub_range(rest, split - i, min_sub);
+        let sub = gen_range_with_nonce(min_sub, max_sub + 1, i as u128);
+        rest -= sub;
+        subs.push(sub.into());
+    };
+    // Fisher-Yates shuffle
+    for i in (1..split).rev() {
+        let j = gen_range_with_nonce(0, i as u128 + 1, (split + i) as u128) as usize;
+        subs.swap(i, j);
+    };
+    subs
 }
 
 pub fn rand_u128() -> u128 {
@@ -36,14 +65,126 @@ pub fn rand_u128() -> u128 {
     u128::from_le_bytes(arr)
 }
 
+/// Random seed is fixed in one execution, hash it with nonce to get independent random numbers
+pub fn rand_u128_with_nonce(nonce: u128) -> u128 {
+    let mut seed = env::random_seed();
+    seed.extend_from_slice(&nonce.to_le_bytes());
+    let hash = env::sha256(&seed);
+    let mut arr: [u8; 16] = Default::default();
+    arr.copy_from_slice(&hash[..16]);
+    u128::from_le_bytes(arr)
+}
+
 pub fn gen_range(start: u128, end: u128) -> u128 {
     rand_u128() % (end - start) + start
 }
 
+pub fn gen_range_with_nonce(start: u128, end: u128, nonce: u128) -> u128 {
+    rand_u128_with_nonce(nonce) % (end - start) + start
+}
+
 pub fn transfer(to: AccountId, amount: Balance) -> Promise {
     Promise::new(to).transfer(amount)
 }
 
+/// Gas a claim with the access key attaches,
+/// fungible token checks the claimer registration and every bundle leg adds one transfer with resolver
+pub fn claim_gas(token: &Token, bundle_legs: usize) -> Gas {
+    let storage_check_gas = match token {
+        Token::FungibleToken => {
+            GAS_FOR_FT_STORAGE_BALANCE_OF.0 + GAS_FOR_FT_STORAGE_BALANCE_BOUNDS.0 + GAS_FOR_RESOLVE_FT_STORAGE_BALANCE_OF_CLAIMER.0
+        },
+        _ => 0
+    };
+    let bundle_gas = (GAS_FOR_FT_TRANSFER.0 + GAS_FOR_RESOLVE_CLAIM_FUNGIBLE_TOKEN_RED_PACKET.0) * bundle_legs as u64;
+    Gas(GAS_FOR_CLAIM_RED_PACKET.0 + storage_check_gas + bundle_gas)
+}
+
+/// Static gas of the payouts a forced unregister makes for the red packet
+pub fn forced_refund_gas(red_packet: &RedPacket) -> Gas {
+    let ft_payout_gas = GAS_FOR_FT_TRANSFER.0 + GAS_FOR_RESOLVE_PAYOUT.0;
+    let mut gas = 0;
+    if !red_packet.is_run_out() {
+        gas += match red_packet.token {
+            Token::NEAR => GAS_FOR_RESOLVE_PAYOUT.0,
+            Token::FungibleToken => ft_payout_gas,
+            Token::MultiToken { .. } => GAS_FOR_MT_TRANSFER.0 + GAS_FOR_RESOLVE_PAYOUT.0,
+            Token::NonFungibleToken => {
+                GAS_FOR_NFT_TRANSFER.0 * red_packet.nft_token_ids.len() as u64 + GAS_FOR_RESOLVE_PAYOUT.0
+            }
+        };
+        gas += ft_payout_gas * red_packet.bundle.len() as u64;
+        // released reserves
+        gas += GAS_FOR_RESOLVE_PAYOUT.0;
+    };
+    if !red_packet.retry_claims.is_empty() {
+        gas += ft_payout_gas;
+    };
+    Gas(gas)
+}
+
+pub fn access_key_allowance_per_claim(token: &Token, bundle_legs: usize) -> Balance {
+    claim_gas(token, bundle_legs).0 as Balance * ACCESS_KEY_GAS_PRICE
+}
+
+pub fn default_access_key_allowance(token: &Token, split: usize) -> Balance {
+    access_key_allowance_per_claim(token, 0) * u128::try_from(split).unwrap()
+}
+
+pub fn access_key_method_names(token: &Token) -> &'static str {
+    match token {
+        // create_account_and_claim is used for near official linkdrop
+        Token::NEAR => "claim_red_packet,create_account_and_claim",
+        _ => "claim_red_packet"
+    }
+}
+
+pub fn add_access_key(public_key: PublicKey, allowance: Balance, method_names: &str) -> Promise {
+    // zero allowance means unlimited
+    require!(allowance > 0, "Access key allowance is 0");
+    Promise::new(env::current_account_id())
+        .add_access_key(
+            public_key,
+            allowance,
+            env::current_account_id(),
+            method_names.into()
+        )
+}
+
+/// Replace the access key to change its allowance
+pub fn reset_access_key(public_key: PublicKey, allowance: Balance, method_names: &str) -> Promise {
+    require!(allowance > 0, "Access key allowance is 0");
+    Promise::new(env::current_account_id())
+        .delete_key(public_key.clone())
+        .add_access_key(
+            public_key,
+            allowance,
+            env::current_account_id(),
+            method_names.into()
+        )
+}
+
+pub fn delete_access_key(public_key: PublicKey) {
+    Promise::new(env::current_account_id()).delete_key(public_key);
+}
+
+/// Transfer NEAR, fungible token or multi token, failed payout is credited to the internal ledger
+pub fn payout(to: AccountId, token: Token, token_id: Option<AccountId>, amount: U128) -> Promise {
+    let transfer_promise = match &token {
+        Token::NEAR => transfer(to.clone(), amount.0),
+        Token::FungibleToken => transfer_ft(to.clone(), amount, token_id.clone().unwrap()),
+        Token::MultiToken { token_id: mt_token_id } => {
+            transfer_mt(to.clone(), amount, mt_token_id.clone(), token_id.clone().unwrap())
+        },
+        Token::NonFungibleToken => panic!("{}", ERR_04_INVALID_PARAMETER)
+    };
+    transfer_promise.then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_RESOLVE_PAYOUT)
+            .resolve_payout(to, token, token_id, amount)
+    )
+}
+
 pub fn transfer_ft(to: AccountId, amount: U128, token_id: AccountId) -> Promise {
     ext_ft::ext(token_id)
         .with_attached_deposit(ONE_YOCTO)
@@ -71,3 +212,153 @@ pub fn transfer_ft_with_resolve_claim_fungible_token_red_packet(
                 )
         )
 }
+
+pub fn transfer_ft_with_resolve_retry_claim(
+    to: AccountId,
+    amount: U128,
+    token_id: AccountId,
+    owner_id: AccountId,
+    public_key: PublicKey
+) -> Promise {
+    transfer_ft(to.clone(), amount, token_id.clone())
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_RETRY_CLAIM)
+                .resolve_retry_claim(
+                    to,
+                    owner_id,
+                    amount,
+                    token_id,
+                    public_key
+                )
+        )
+}
+
+/// Check the claimer registration and the storage balance bounds on the token contract before transfer
+pub fn transfer_ft_with_storage_check_and_resolve_claim_fungible_token_red_packet(
+    to: AccountId,
+    amount: U128,
+    token_id: AccountId,
+    owner_id: AccountId,
+    public_key: PublicKey,
+    storage_deposit: U128
+) -> Promise {
+    ext_ft::ext(token_id.clone())
+        .with_static_gas(GAS_FOR_FT_STORAGE_BALANCE_OF)
+        .storage_balance_of(to.clone())
+        .and(
+            ext_ft::ext(token_id.clone())
+                .with_static_gas(GAS_FOR_FT_STORAGE_BALANCE_BOUNDS)
+                .storage_balance_bounds()
+        )
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_FT_STORAGE_BALANCE_OF_CLAIMER)
+                .resolve_ft_storage_balance_of_claimer(
+                    to,
+                    owner_id,
+                    amount,
+                    token_id,
+                    public_key,
+                    storage_deposit
+                )
+        )
+}
+
+pub fn storage_deposit_and_transfer_ft_with_resolve_claim_fungible_token_red_packet(
+    to: AccountId,
+    amount: U128,
+    token_id: AccountId,
+    owner_id: AccountId,
+    public_key: PublicKey,
+    storage_deposit: U128
+) -> Promise {
+    ext_ft::ext(token_id.clone())
+        .with_attached_deposit(storage_deposit.0)
+        .with_static_gas(GAS_FOR_FT_STORAGE_DEPOSIT)
+        .storage_deposit(Some(to.clone()), Some(true))
+        .then(transfer_ft(to.clone(), amount, token_id.clone()))
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_CLAIM_FUNGIBLE_TOKEN_RED_PACKET)
+                .resolve_claim_fungible_token_red_packet(
+                    to,
+                    owner_id,
+                    amount,
+                    token_id,
+                    public_key
+                )
+        )
+}
+
+pub fn transfer_nft(to: AccountId, nft_token_id: TokenId, token_id: AccountId) -> Promise {
+    ext_nft::ext(token_id)
+        .with_attached_deposit(ONE_YOCTO)
+        .with_static_gas(GAS_FOR_NFT_TRANSFER)
+        .nft_transfer(to, nft_token_id, None, None)
+}
+
+/// Transfer non fungible tokens of one contract at once, failed transfers are credited to the internal ledger
+pub fn payout_nft(to: AccountId, token_id: AccountId, nft_token_ids: Vec<TokenId>) -> Promise {
+    let transfer_promise = nft_token_ids
+        .iter()
+        .map(|nft_token_id| transfer_nft(to.clone(), nft_token_id.clone(), token_id.clone()))
+        .reduce(|promise, transfer_promise| promise.and(transfer_promise))
+        .expect(ERR_04_INVALID_PARAMETER);
+    transfer_promise.then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_RESOLVE_PAYOUT)
+            .resolve_payout_nft(to, token_id, nft_token_ids)
+    )
+}
+
+pub fn transfer_nft_with_resolve_claim_non_fungible_token_red_packet(
+    to: AccountId,
+    nft_token_id: TokenId,
+    token_id: AccountId,
+    owner_id: AccountId,
+    public_key: PublicKey
+) -> Promise {
+    transfer_nft(to.clone(), nft_token_id.clone(), token_id.clone())
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_CLAIM_NON_FUNGIBLE_TOKEN_RED_PACKET)
+                .resolve_claim_non_fungible_token_red_packet(
+                    to,
+                    owner_id,
+                    nft_token_id,
+                    token_id,
+                    public_key
+                )
+        )
+}
+
+pub fn transfer_mt(to: AccountId, amount: U128, mt_token_id: String, token_id: AccountId) -> Promise {
+    ext_mt::ext(token_id)
+        .with_attached_deposit(ONE_YOCTO)
+        .with_static_gas(GAS_FOR_MT_TRANSFER)
+        .mt_transfer(to, mt_token_id, amount, None, None)
+}
+
+pub fn transfer_mt_with_resolve_claim_multi_token_red_packet(
+    to: AccountId,
+    amount: U128,
+    mt_token_id: String,
+    token_id: AccountId,
+    owner_id: AccountId,
+    public_key: PublicKey
+) -> Promise {
+    transfer_mt(to.clone(), amount, mt_token_id.clone(), token_id.clone())
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_CLAIM_MULTI_TOKEN_RED_PACKET)
+                .resolve_claim_multi_token_red_packet(
+                    to,
+                    owner_id,
+                    amount,
+                    token_id,
+                    mt_token_id,
+                    public_key
+                )
+        )
+}
@@ -0,0 +1,108 @@
+use crate::errors::*;
+
+use std::collections::{HashMap, HashSet};
+use near_sdk::{AccountId, env};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Serialize, Deserialize};
+
+
+#[derive(BorshDeserialize,BorshSerialize,Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+pub enum WhiteList {
+    Accounts(HashSet<AccountId>),
+    /// Airdrop mode, every account claims exactly the listed amount
+    Amounts(HashMap<AccountId, U128>),
+    /// Root of a sha256 merkle tree, claimers prove eligibility with [MerkleProof]
+    MerkleRoot(Base64VecU8)
+}
+
+/// Leaf is `sha256(claimer_id)`, or `sha256("{claimer_id}:{amount}")` if amount is committed.
+/// Parent node is sha256 of the two sorted child nodes.
+#[derive(Serialize,Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MerkleProof {
+    pub amount: Option<U128>,
+    pub proof: Vec<Base64VecU8>
+}
+
+impl MerkleProof {
+    pub fn verify(&self, root: &[u8], claimer_id: &AccountId) -> bool {
+        let leaf = match self.amount {
+            None => claimer_id.to_string(),
+            Some(amount) => format!("{}:{}", claimer_id, amount.0)
+        };
+        let mut node = env::sha256(leaf.as_bytes());
+        for sibling in &self.proof {
+            let mut pair = Vec::with_capacity(node.len() + sibling.0.len());
+            if node <= sibling.0 {
+                pair.extend_from_slice(&node);
+                pair.extend_from_slice(&sibling.0);
+            } else {
+                pair.extend_from_slice(&sibling.0);
+                pair.extend_from_slice(&node);
+            }
+            node = env::sha256(&pair);
+        }
+        node == root
+    }
+}
+
+impl WhiteList {
+    pub fn is_valid(&self, split: usize, balance: u128) -> bool {
+        match self {
+            WhiteList::Accounts(accounts) => accounts.len() == split,
+            WhiteList::Amounts(amounts) => {
+                if amounts.len() != split || amounts.values().any(|amount| amount.0 == 0) {
+                    return false;
+                }
+                amounts
+                    .values()
+                    .try_fold(0u128, |total, amount| total.checked_add(amount.0))
+                    == Some(balance)
+            },
+            WhiteList::MerkleRoot(root) => root.0.len() == 32
+        }
+    }
+
+    /// Whether claim amounts may be fixed by the white list
+    pub fn has_amounts(&self) -> bool {
+        match self {
+            WhiteList::Accounts(_) => false,
+            WhiteList::Amounts(_) | WhiteList::MerkleRoot(_) => true
+        }
+    }
+
+    /// Remove claimer from white list, merkle root white list requires a valid proof.
+    /// Return the listed amount in airdrop mode, else return [None].
+    pub fn remove(&mut self, claimer_id: &AccountId, merkle_proof: Option<MerkleProof>) -> Result<Option<U128>, &'static str> {
+        match self {
+            WhiteList::Accounts(accounts) => {
+                if !accounts.remove(claimer_id) {
+                    return Err(ERR_08_CLAIMER_NOT_IN_WHITE_LIST);
+                };
+                Ok(None)
+            },
+            WhiteList::Amounts(amounts) => {
+                match amounts.remove(claimer_id) {
+                    None => Err(ERR_08_CLAIMER_NOT_IN_WHITE_LIST),
+                    Some(amount) => Ok(Some(amount))
+                }
+            },
+            WhiteList::MerkleRoot(root) => {
+                match merkle_proof {
+                    Some(merkle_proof) if merkle_proof.verify(&root.0, claimer_id) => Ok(merkle_proof.amount),
+                    _ => Err(ERR_08_CLAIMER_NOT_IN_WHITE_LIST)
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            WhiteList::Accounts(accounts) => accounts.clear(),
+            WhiteList::Amounts(amounts) => amounts.clear(),
+            WhiteList::MerkleRoot(_) => ()
+        }
+    }
+}